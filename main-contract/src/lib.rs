@@ -2,8 +2,42 @@ mod post;
 mod user;
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LookupMap, Vector};
-use near_sdk::{env, near_bindgen, require, AccountId, BorshStorageKey, PanicOnDefault};
+use near_sdk::collections::{LookupMap, TreeMap, UnorderedMap, UnorderedSet, Vector};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{
+    env, near_bindgen, require, AccountId, Balance, BorshStorageKey, PanicOnDefault, Promise,
+};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+const DEFAULT_PAGE_LIMIT: u64 = 20;
+const MAX_PAGE_LIMIT: u64 = 100;
+const DEFAULT_THREAD_DEPTH: u64 = 3;
+const MAX_THREAD_DEPTH: u64 = 5;
+// Limit on the number of replies returned in one comment thread
+const MAX_THREAD_REPLIES: u64 = 100;
+// Reaction kind that post likes are counted as
+const DEFAULT_REACTION: &str = "like";
+const MAX_REACTION_KIND_LENGTH: usize = 32;
+const MAX_HASHTAG_LENGTH: usize = 64;
+// Hashtags past this many in a post are not indexed
+const MAX_HASHTAGS_PER_POST: usize = 10;
+const NANOSECONDS_PER_HOUR: u64 = 3_600_000_000_000;
+const DEFAULT_TRENDING_WINDOW_HOURS: u64 = 24;
+// Hourly hashtag usage is only kept for this long
+const MAX_TRENDING_WINDOW_HOURS: u64 = 168;
+const DEFAULT_TRENDING_LIMIT: u64 = 10;
+// Stale hashtag usage entries dropped per post creation or edit
+const MAX_PRUNED_HASHTAG_ENTRIES: u64 = 20;
+// Mentions past this many in a post or comment are ignored
+const MAX_MENTIONS_PER_RECORD: usize = 10;
+const MAX_ATTACHMENTS: usize = 4;
+// Limit on the combined size of the text fields of a post's or comment's attachments
+const MAX_ATTACHMENT_METADATA_BYTES: usize = 2048;
+const MIN_POLL_OPTIONS: usize = 2;
+const MAX_POLL_OPTIONS: usize = 10;
+const MAX_POLL_OPTION_LENGTH: usize = 100;
 
 #[derive(BorshStorageKey, BorshSerialize)]
 pub enum StorageKeys {
@@ -12,6 +46,69 @@ pub enum StorageKeys {
     AllPosts,
     PostLikes,
     PostComments,
+    FollowEdges,
+    Following,
+    Followers,
+    FollowingOf { account_hash: Vec<u8> },
+    FollowersOf { account_hash: Vec<u8> },
+    PostLikeIds,
+    PostLikers,
+    PostLikersOf { post_id: u64 },
+    Comments,
+    PostCommentIds,
+    PostCommentIdsOf { post_id: u64 },
+    Posts,
+    UserPosts,
+    UserPostsOf { account_hash: Vec<u8> },
+    Moderators,
+    PostStorageDeposits,
+    PostRevisions,
+    PostRevisionsOf { post_id: u64 },
+    CommentEditedAt,
+    CommentParents,
+    CommentReplies,
+    CommentRepliesOf { comment_id: u64 },
+    CommentLikers,
+    CommentLikersOf { comment_id: u64 },
+    PostTopComments,
+    PostTopCommentsOf { post_id: u64 },
+    ReactionKinds,
+    PostReactions,
+    PostReactionsOf { post_id: u64 },
+    Reposts,
+    RepostIds,
+    PostReposts,
+    PostRepostsOf { post_id: u64 },
+    QuotedPostIds,
+    PostQuotes,
+    PostQuotesOf { post_id: u64 },
+    HashtagPosts,
+    HashtagPostsOf { tag_hash: Vec<u8> },
+    HashtagUsage,
+    HashtagUsageOf { hour: u64 },
+    Mentions,
+    MentionsOf { account_hash: Vec<u8> },
+    PostMentions,
+    CommentMentions,
+    PostAttachments,
+    CommentAttachments,
+    PostPolls,
+    PollVotes,
+    PollVotesOf { post_id: u64 },
+    PostHashtagHours,
+    PostStoragePayers,
+    DeletedPostIds,
+    HashtagHourPosts,
+    PostReactionCounts,
+    DeletedCommentIds,
+}
+
+// A page of results, pass `next_cursor` back to fetch the following page
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<u64>,
 }
 
 #[near_bindgen]
@@ -19,8 +116,106 @@ pub enum StorageKeys {
 pub struct Contract {
     // User fields
     user_list: LookupMap<AccountId, user::UserAccountDetail>,
-    user_followers: Vector<user::UserFollowers>,
+    // (account, followed account) -> follow_id
+    follow_edges: LookupMap<(AccountId, AccountId), u64>,
+    // account -> follow_id -> followed account, ordered by follow time
+    following: LookupMap<AccountId, TreeMap<u64, AccountId>>,
+    // account -> follow_id -> follower account, ordered by follow time
+    followers: LookupMap<AccountId, TreeMap<u64, AccountId>>,
+    follow_counter: u64,
+    // Follow edges from before the follow graph was indexed, drained by `migrate_follow_graph`
+    legacy_user_followers: Vector<user::UserFollowers>,
+    // Accounts allowed to delete any post
+    moderators: UnorderedSet<AccountId>,
     // Post fields
+    // post_id -> post, the tree keeps post_ids in chronological order
+    posts: TreeMap<u64, post::PostDetail>,
+    // author -> ordered set of their post_ids and repost_ids
+    user_posts: LookupMap<AccountId, TreeMap<u64, ()>>,
+    // repost_id -> repost, repost_ids are taken from the post_id sequence
+    reposts: LookupMap<u64, post::Repost>,
+    // (post_id, reposter) -> repost_id
+    repost_ids: LookupMap<(u64, AccountId), u64>,
+    // post_id -> repost_id -> reposter, len() is the repost count
+    post_reposts: LookupMap<u64, TreeMap<u64, AccountId>>,
+    // post_id -> storage cost paid when the post was created, refunded when it is deleted
+    post_storage_deposits: LookupMap<u64, Balance>,
+    // post_id -> account that paid the storage deposit, refunded when the post is deleted
+    post_storage_payers: LookupMap<u64, AccountId>,
+    // Deleted posts whose likes, comments and other records are still being removed by `clear_deleted_posts`
+    deleted_post_ids: Vector<u64>,
+    // Deleted comments whose replies and likes are still being removed by `clear_deleted_comments`
+    deleted_comment_ids: Vector<u64>,
+    // quoting post_id -> quoted post_id
+    quoted_post_ids: LookupMap<u64, u64>,
+    // quoted post_id -> ordered set of quoting post_ids, len() is the quote count
+    post_quotes: LookupMap<u64, TreeMap<u64, ()>>,
+    // hashtag -> ordered set of post_ids using it
+    hashtag_posts: LookupMap<String, TreeMap<u64, ()>>,
+    // hour -> hashtag -> number of posts that started using it that hour
+    hashtag_usage: TreeMap<u64, UnorderedMap<String, u64>>,
+    // post_id -> (hashtag, hour its use by the post was counted in)
+    post_hashtag_hours: LookupMap<u64, Vec<(String, u64)>>,
+    // Ordered set of (hour, post_id) for the hours in `post_hashtag_hours`, so entries can be
+    // dropped once their hour leaves the longest trending window
+    hashtag_hour_posts: TreeMap<(u64, u64), ()>,
+    // post_id -> contents the post had before each edit, oldest first
+    post_revisions: LookupMap<u64, Vector<post::PostRevision>>,
+    // Posts from before posts were keyed by post_id, drained by `migrate_posts`
+    legacy_all_posts: Vector<post::PostDetail>,
+    // (post_id, liker) -> like_id
+    post_like_ids: LookupMap<(u64, AccountId), u64>,
+    // post_id -> like_id -> like, ordered by like time, len() is the like count
+    post_likers: LookupMap<u64, TreeMap<u64, post::PostLikes>>,
+    like_counter: u64,
+    // Likes from before likes were indexed per post, drained by `migrate_post_likes`
+    legacy_post_likes: Vector<post::PostLikes>,
+    // Reaction kinds accounts can react to posts with, always includes `DEFAULT_REACTION`
+    reaction_kinds: UnorderedSet<String>,
+    // post_id -> reactor -> reaction kind, likes are kept in the like index instead
+    post_reactions: LookupMap<u64, UnorderedMap<AccountId, String>>,
+    // post_id -> reaction kind -> number of reactions of that kind, likes are counted by the like index
+    reaction_counts: LookupMap<u64, BTreeMap<String, u64>>,
+    // comment_id -> comment
+    comments: LookupMap<u64, post::PostComment>,
+    // comment_id -> time of the last edit
+    comment_edited_at: LookupMap<u64, u64>,
+    // reply comment_id -> parent comment_id
+    comment_parents: LookupMap<u64, u64>,
+    // comment_id -> ordered set of reply comment_ids, len() is the reply count
+    comment_replies: LookupMap<u64, TreeMap<u64, ()>>,
+    // comment_id -> accounts that liked the comment
+    comment_likers: LookupMap<u64, UnorderedSet<AccountId>>,
+    // post_id -> ordered set of (like count, comment_id), ranks the post's comments by likes
+    post_top_comments: LookupMap<u64, TreeMap<(u64, u64), ()>>,
+    // post_id -> ordered set of comment_ids, len() is the comment count
+    post_comment_ids: LookupMap<u64, TreeMap<u64, ()>>,
+    // Comments from before comments were indexed per post, drained by `migrate_post_comments`
+    legacy_post_comments: Vector<post::PostComment>,
+    // post_id -> media attached to the post
+    post_attachments: LookupMap<u64, Vec<post::Attachment>>,
+    // comment_id -> media attached to the comment
+    comment_attachments: LookupMap<u64, Vec<post::Attachment>>,
+    // post_id -> poll attached to the post
+    post_polls: LookupMap<u64, post::Poll>,
+    // post_id -> voter -> index of the option they voted for
+    poll_votes: LookupMap<u64, UnorderedMap<AccountId, u64>>,
+    // mentioned account -> mention_id -> mention, ordered by mention time
+    mentions: LookupMap<AccountId, TreeMap<u64, post::Mention>>,
+    // post_id -> (mentioned account, mention_id) for each account mentioned in the post
+    post_mentions: LookupMap<u64, Vec<(AccountId, u64)>>,
+    // comment_id -> (mentioned account, mention_id) for each account mentioned in the comment
+    comment_mentions: LookupMap<u64, Vec<(AccountId, u64)>>,
+    mention_counter: u64,
+    post_counter: u64,
+    comment_counter: u64,
+}
+
+// Contract state layout before the follow graph was indexed
+#[derive(BorshDeserialize)]
+struct OldContract {
+    user_list: LookupMap<AccountId, user::UserAccountDetail>,
+    user_followers: Vector<user::UserFollowers>,
     all_posts: Vector<post::PostDetail>,
     post_likes: Vector<post::PostLikes>,
     post_comments: Vector<post::PostComment>,
@@ -36,15 +231,275 @@ impl Contract {
         require!(!env::state_exists(), "The contract is already initialized");
         Self {
             user_list: LookupMap::new(StorageKeys::UserList),
-            user_followers: Vector::new(StorageKeys::UserFollowers),
-            all_posts: Vector::new(StorageKeys::AllPosts),
-            post_likes: Vector::new(StorageKeys::PostLikes),
-            post_comments: Vector::new(StorageKeys::PostComments),
+            follow_edges: LookupMap::new(StorageKeys::FollowEdges),
+            following: LookupMap::new(StorageKeys::Following),
+            followers: LookupMap::new(StorageKeys::Followers),
+            follow_counter: 0,
+            legacy_user_followers: Vector::new(StorageKeys::UserFollowers),
+            moderators: UnorderedSet::new(StorageKeys::Moderators),
+            posts: TreeMap::new(StorageKeys::Posts),
+            user_posts: LookupMap::new(StorageKeys::UserPosts),
+            reposts: LookupMap::new(StorageKeys::Reposts),
+            repost_ids: LookupMap::new(StorageKeys::RepostIds),
+            post_reposts: LookupMap::new(StorageKeys::PostReposts),
+            post_storage_deposits: LookupMap::new(StorageKeys::PostStorageDeposits),
+            post_storage_payers: LookupMap::new(StorageKeys::PostStoragePayers),
+            deleted_post_ids: Vector::new(StorageKeys::DeletedPostIds),
+            deleted_comment_ids: Vector::new(StorageKeys::DeletedCommentIds),
+            quoted_post_ids: LookupMap::new(StorageKeys::QuotedPostIds),
+            post_quotes: LookupMap::new(StorageKeys::PostQuotes),
+            hashtag_posts: LookupMap::new(StorageKeys::HashtagPosts),
+            hashtag_usage: TreeMap::new(StorageKeys::HashtagUsage),
+            post_hashtag_hours: LookupMap::new(StorageKeys::PostHashtagHours),
+            hashtag_hour_posts: TreeMap::new(StorageKeys::HashtagHourPosts),
+            post_revisions: LookupMap::new(StorageKeys::PostRevisions),
+            legacy_all_posts: Vector::new(StorageKeys::AllPosts),
+            post_like_ids: LookupMap::new(StorageKeys::PostLikeIds),
+            post_likers: LookupMap::new(StorageKeys::PostLikers),
+            like_counter: 0,
+            legacy_post_likes: Vector::new(StorageKeys::PostLikes),
+            reaction_kinds: default_reaction_kinds(),
+            post_reactions: LookupMap::new(StorageKeys::PostReactions),
+            reaction_counts: LookupMap::new(StorageKeys::PostReactionCounts),
+            comments: LookupMap::new(StorageKeys::Comments),
+            comment_edited_at: LookupMap::new(StorageKeys::CommentEditedAt),
+            comment_parents: LookupMap::new(StorageKeys::CommentParents),
+            comment_replies: LookupMap::new(StorageKeys::CommentReplies),
+            comment_likers: LookupMap::new(StorageKeys::CommentLikers),
+            post_top_comments: LookupMap::new(StorageKeys::PostTopComments),
+            post_comment_ids: LookupMap::new(StorageKeys::PostCommentIds),
+            legacy_post_comments: Vector::new(StorageKeys::PostComments),
+            post_attachments: LookupMap::new(StorageKeys::PostAttachments),
+            comment_attachments: LookupMap::new(StorageKeys::CommentAttachments),
+            post_polls: LookupMap::new(StorageKeys::PostPolls),
+            poll_votes: LookupMap::new(StorageKeys::PollVotes),
+            mentions: LookupMap::new(StorageKeys::Mentions),
+            post_mentions: LookupMap::new(StorageKeys::PostMentions),
+            comment_mentions: LookupMap::new(StorageKeys::CommentMentions),
+            mention_counter: 0,
             post_counter: 0,
             comment_counter: 0,
         }
     }
 
+    // Migrate state from the previous contract layout
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let old: OldContract = env::state_read().expect("Old state doesn't exist");
+        Self {
+            user_list: old.user_list,
+            follow_edges: LookupMap::new(StorageKeys::FollowEdges),
+            following: LookupMap::new(StorageKeys::Following),
+            followers: LookupMap::new(StorageKeys::Followers),
+            // Legacy edges keep their position as follow_id, new follows are numbered after them
+            follow_counter: old.user_followers.len(),
+            legacy_user_followers: old.user_followers,
+            moderators: UnorderedSet::new(StorageKeys::Moderators),
+            posts: TreeMap::new(StorageKeys::Posts),
+            user_posts: LookupMap::new(StorageKeys::UserPosts),
+            reposts: LookupMap::new(StorageKeys::Reposts),
+            repost_ids: LookupMap::new(StorageKeys::RepostIds),
+            post_reposts: LookupMap::new(StorageKeys::PostReposts),
+            post_storage_deposits: LookupMap::new(StorageKeys::PostStorageDeposits),
+            post_storage_payers: LookupMap::new(StorageKeys::PostStoragePayers),
+            deleted_post_ids: Vector::new(StorageKeys::DeletedPostIds),
+            deleted_comment_ids: Vector::new(StorageKeys::DeletedCommentIds),
+            quoted_post_ids: LookupMap::new(StorageKeys::QuotedPostIds),
+            post_quotes: LookupMap::new(StorageKeys::PostQuotes),
+            hashtag_posts: LookupMap::new(StorageKeys::HashtagPosts),
+            hashtag_usage: TreeMap::new(StorageKeys::HashtagUsage),
+            post_hashtag_hours: LookupMap::new(StorageKeys::PostHashtagHours),
+            hashtag_hour_posts: TreeMap::new(StorageKeys::HashtagHourPosts),
+            post_revisions: LookupMap::new(StorageKeys::PostRevisions),
+            legacy_all_posts: old.all_posts,
+            post_like_ids: LookupMap::new(StorageKeys::PostLikeIds),
+            post_likers: LookupMap::new(StorageKeys::PostLikers),
+            like_counter: old.post_likes.len(),
+            legacy_post_likes: old.post_likes,
+            reaction_kinds: default_reaction_kinds(),
+            post_reactions: LookupMap::new(StorageKeys::PostReactions),
+            reaction_counts: LookupMap::new(StorageKeys::PostReactionCounts),
+            comments: LookupMap::new(StorageKeys::Comments),
+            comment_edited_at: LookupMap::new(StorageKeys::CommentEditedAt),
+            comment_parents: LookupMap::new(StorageKeys::CommentParents),
+            comment_replies: LookupMap::new(StorageKeys::CommentReplies),
+            comment_likers: LookupMap::new(StorageKeys::CommentLikers),
+            post_top_comments: LookupMap::new(StorageKeys::PostTopComments),
+            post_comment_ids: LookupMap::new(StorageKeys::PostCommentIds),
+            legacy_post_comments: old.post_comments,
+            post_attachments: LookupMap::new(StorageKeys::PostAttachments),
+            comment_attachments: LookupMap::new(StorageKeys::CommentAttachments),
+            post_polls: LookupMap::new(StorageKeys::PostPolls),
+            poll_votes: LookupMap::new(StorageKeys::PollVotes),
+            mentions: LookupMap::new(StorageKeys::Mentions),
+            post_mentions: LookupMap::new(StorageKeys::PostMentions),
+            comment_mentions: LookupMap::new(StorageKeys::CommentMentions),
+            mention_counter: 0,
+            post_counter: old.post_counter,
+            comment_counter: old.comment_counter,
+        }
+    }
+
+    // Move up to `limit` legacy follow edges into the follow graph, returns how many are left
+    #[private]
+    pub fn migrate_follow_graph(&mut self, limit: u64) -> u64 {
+        for _ in 0..limit {
+            let follow_id = self.legacy_user_followers.len();
+            match self.legacy_user_followers.pop() {
+                None => break,
+                Some(edge) => self.insert_follow_edge(
+                    follow_id,
+                    &edge.user_account_id,
+                    &edge.follower_account_id,
+                ),
+            }
+        }
+        self.legacy_user_followers.len()
+    }
+
+    // Recompute the stored follower and following counts of `account_ids` from the follow graph
+    #[private]
+    pub fn recompute_follow_counts(&mut self, account_ids: Vec<AccountId>) {
+        for account_id in account_ids {
+            let following_count = self.following_of(&account_id).len() as u32;
+            let followers_count = self.followers_of(&account_id).len() as u32;
+            self.update_account(&account_id, |account| {
+                account.following_count = following_count;
+                account.followers_count = followers_count;
+            });
+        }
+    }
+
+    // Allow an account to delete any post
+    #[private]
+    pub fn add_moderator(&mut self, account_id: AccountId) -> bool {
+        self.moderators.insert(&account_id)
+    }
+
+    // Revoke an account's moderator rights
+    #[private]
+    pub fn remove_moderator(&mut self, account_id: AccountId) -> bool {
+        self.moderators.remove(&account_id)
+    }
+
+    // Get moderator list
+    pub fn get_moderators(&self) -> Vec<AccountId> {
+        self.moderators.to_vec()
+    }
+
+    // Allow accounts to react to posts with `kind`
+    #[private]
+    pub fn add_reaction_kind(&mut self, kind: String) -> bool {
+        require!(
+            !kind.is_empty() && kind.len() <= MAX_REACTION_KIND_LENGTH,
+            "Reaction kind must be between 1 and 32 bytes!"
+        );
+        self.reaction_kinds.insert(&kind)
+    }
+
+    // Stop accepting new reactions of `kind`, existing ones are kept and still counted
+    #[private]
+    pub fn remove_reaction_kind(&mut self, kind: String) -> bool {
+        require!(
+            kind != DEFAULT_REACTION,
+            "The default reaction kind cannot be removed!"
+        );
+        self.reaction_kinds.remove(&kind)
+    }
+
+    // Get the reaction kinds accounts can react to posts with
+    pub fn get_reaction_kinds(&self) -> Vec<String> {
+        self.reaction_kinds.to_vec()
+    }
+
+    // Queue the likes and comments attached to post IDs that were issued but no longer exist
+    // for removal by `clear_deleted_posts`, returns how many post IDs were queued
+    #[private]
+    pub fn purge_orphan_interactions(&mut self, post_ids: Vec<u64>) -> u64 {
+        require!(
+            self.legacy_all_posts.is_empty(),
+            "Posts migration in progress!"
+        );
+        let mut queued = 0;
+        for post_id in post_ids {
+            // IDs not issued yet and repost IDs never carry interactions of their own
+            if post_id <= self.post_counter
+                && !self.posts.contains_key(&post_id)
+                && !self.reposts.contains_key(&post_id)
+            {
+                self.deleted_post_ids.push(&post_id);
+                queued += 1;
+            }
+        }
+        queued
+    }
+
+    // Remove up to `limit` records left behind by deleted posts, returns how many deleted posts are left
+    #[private]
+    pub fn clear_deleted_posts(&mut self, limit: u64) -> u64 {
+        let mut budget = limit;
+        while budget > 0 {
+            let post_id = match self
+                .deleted_post_ids
+                .len()
+                .checked_sub(1)
+                .and_then(|last| self.deleted_post_ids.get(last))
+            {
+                None => break,
+                Some(post_id) => post_id,
+            };
+            // A post that exists again keeps its records
+            if self.posts.contains_key(&post_id) {
+                self.deleted_post_ids.pop();
+                continue;
+            }
+            let removed = self.clear_post_records(post_id, budget);
+            if removed < budget {
+                self.deleted_post_ids.pop();
+            }
+            budget -= removed;
+        }
+        self.deleted_post_ids.len()
+    }
+
+    // Move up to `limit` legacy posts into the post map, returns how many are left
+    #[private]
+    pub fn migrate_posts(&mut self, limit: u64) -> u64 {
+        for _ in 0..limit {
+            match self.legacy_all_posts.pop() {
+                None => break,
+                Some(post) => self.insert_post(&post),
+            }
+        }
+        self.legacy_all_posts.len()
+    }
+
+    // Move up to `limit` legacy likes into the per-post like index, returns how many are left
+    #[private]
+    pub fn migrate_post_likes(&mut self, limit: u64) -> u64 {
+        for _ in 0..limit {
+            let like_id = self.legacy_post_likes.len();
+            match self.legacy_post_likes.pop() {
+                None => break,
+                Some(like) => self.insert_post_like(like_id, &like),
+            }
+        }
+        self.legacy_post_likes.len()
+    }
+
+    // Move up to `limit` legacy comments into the per-post comment index, returns how many are left
+    #[private]
+    pub fn migrate_post_comments(&mut self, limit: u64) -> u64 {
+        for _ in 0..limit {
+            match self.legacy_post_comments.pop() {
+                None => break,
+                Some(comment) => self.insert_comment(&comment),
+            }
+        }
+        self.legacy_post_comments.len()
+    }
+
     // Check if user exist
     pub fn is_user_exists(&self, address: AccountId) -> bool {
         self.user_list.contains_key(&address)
@@ -74,8 +529,9 @@ impl Contract {
                 url: url.unwrap_or("".into()),
                 description: description.unwrap_or("".into()),
                 created_at: env::block_timestamp(),
-                followers_count: 0,
-                following_count: 0,
+                // Edges recorded before the account was created still count
+                followers_count: self.followers_of(&address).len() as u32,
+                following_count: self.following_of(&address).len() as u32,
             },
         );
 
@@ -128,58 +584,97 @@ impl Contract {
         &self,
         user_account_id: &AccountId,
         destination_account_id: &AccountId,
-    ) -> Option<usize> {
-        self.user_followers.iter().position(|u| {
-            u.user_account_id == *user_account_id
-                && u.follower_account_id == *destination_account_id
-        })
+    ) -> bool {
+        self.follow_edges
+            .contains_key(&(user_account_id.clone(), destination_account_id.clone()))
     }
 
-    // Follow and unfollow user
-    pub fn follow_user(&mut self, address: AccountId) {
+    // Follow user, returns false if the user was already followed
+    pub fn follow(&mut self, address: AccountId) -> bool {
+        require!(
+            self.legacy_user_followers.is_empty(),
+            "Follow graph migration in progress!"
+        );
         let user_account_id = env::signer_account_id();
         let destination_account_id = address;
-        let is_followed = self.is_user_followed(&user_account_id, &destination_account_id);
+        if self.is_user_followed(&user_account_id, &destination_account_id) {
+            return false;
+        }
+        self.assert_registered(&user_account_id);
+        require!(
+            self.is_user_exists(destination_account_id.clone()),
+            "User does not exist!"
+        );
+        require!(
+            user_account_id != destination_account_id,
+            "You cannot follow yourself!"
+        );
 
-        match is_followed {
-            None => {
-                self.user_followers.push(&user::UserFollowers {
-                    user_account_id,
-                    follower_account_id: destination_account_id,
-                });
-            }
-            Some(u) => {
-                self.user_followers.swap_remove(u as u64);
-            }
+        self.follow_counter += 1;
+        self.insert_follow_edge(
+            self.follow_counter,
+            &user_account_id,
+            &destination_account_id,
+        );
+        true
+    }
+
+    // Unfollow user, returns false if the user was not followed
+    pub fn unfollow(&mut self, address: AccountId) -> bool {
+        require!(
+            self.legacy_user_followers.is_empty(),
+            "Follow graph migration in progress!"
+        );
+        let user_account_id = env::signer_account_id();
+        if !self.is_user_followed(&user_account_id, &address) {
+            return false;
+        }
+        self.remove_follow_edge(&user_account_id, &address);
+        true
+    }
+
+    // Follow and unfollow user
+    // Deprecated: toggles on every call so retries undo each other, use `follow` and `unfollow`
+    pub fn follow_user(&mut self, address: AccountId) {
+        let user_account_id = env::signer_account_id();
+        if self.is_user_followed(&user_account_id, &address) {
+            self.unfollow(address);
+        } else {
+            self.follow(address);
         }
     }
 
-    // Get user following list
-    pub fn get_user_following_list(&self, user_account_id: AccountId) -> Vec<user::UserFollowList> {
+    // Get user following list in follow order, starting after `after_follow_id`
+    pub fn get_user_following_list(
+        &self,
+        user_account_id: AccountId,
+        after_follow_id: Option<u64>,
+        limit: Option<u64>,
+    ) -> Page<user::UserFollowList> {
         require!(
             self.is_user_exists(user_account_id.clone()),
             "User does not exist!"
         );
+        let (following, next_cursor) = paginate(
+            &self.following_of(&user_account_id),
+            after_follow_id,
+            limit,
+            false,
+        );
         let mut follow_list: Vec<user::UserFollowList> = vec![];
-        // self.user_followers
-        //     .iter()
-        //     .filter(|u| u.user_account_id == user_account_id)
-        //     .map(|u| u.follower_account_id.to_string())
-        //     .collect::<Vec<String>>()
-        for (_, user) in self
-            .user_followers
-            .iter()
-            .filter(|u| u.user_account_id == user_account_id)
-            .enumerate()
-        {
-            let profile = self.get_account_details(user.follower_account_id.clone()).unwrap();
+        for (_, followed_account_id) in following {
+            // Legacy edges may point at accounts that were never created
+            let profile_image_url = self.profile_of(&followed_account_id).profile_image_url;
             follow_list.push(user::UserFollowList {
-                profile_image_url: profile.profile_image_url,
-                user_account_id: user.follower_account_id,
-                is_followed: true
+                profile_image_url,
+                user_account_id: followed_account_id,
+                is_followed: true,
             });
         }
-        follow_list
+        Page {
+            items: follow_list,
+            next_cursor,
+        }
     }
 
     // Get user following count
@@ -188,44 +683,43 @@ impl Contract {
             self.is_user_exists(user_account_id.clone()),
             "User does not exist!"
         );
-        self.user_followers
-            .iter()
-            .filter(|u| u.user_account_id == user_account_id)
-            .count() as u64
+        self.following
+            .get(&user_account_id)
+            .map_or(0, |following| following.len())
     }
 
-    // Get user followers list
-    pub fn get_user_followers_list(&self, user_account_id: AccountId) -> Vec<user::UserFollowList> {
+    // Get user followers list in follow order, starting after `after_follow_id`
+    pub fn get_user_followers_list(
+        &self,
+        user_account_id: AccountId,
+        after_follow_id: Option<u64>,
+        limit: Option<u64>,
+    ) -> Page<user::UserFollowList> {
         require!(
             self.is_user_exists(user_account_id.clone()),
             "User does not exist!"
         );
+        let (followers, next_cursor) = paginate(
+            &self.followers_of(&user_account_id),
+            after_follow_id,
+            limit,
+            false,
+        );
         let mut follow_list: Vec<user::UserFollowList> = vec![];
-        // self.user_followers
-        //     .iter()
-        //     .filter(|u| u.follower_account_id == user_account_id)
-        //     .map(|u| u.user_account_id.to_string())
-        //     .collect::<Vec<String>>()
-
-        for (_, user) in self
-            .user_followers
-            .iter()
-            .filter(|u| u.follower_account_id == user_account_id)
-            .enumerate()
-        {
-            let profile = self.get_account_details(user.user_account_id.clone()).unwrap();
-            let is_followed = self.is_user_followed(&user_account_id, &user.user_account_id);
+        for (_, follower_account_id) in followers {
+            // Legacy edges may point at accounts that were never created
+            let profile_image_url = self.profile_of(&follower_account_id).profile_image_url;
+            let is_followed = self.is_user_followed(&user_account_id, &follower_account_id);
             follow_list.push(user::UserFollowList {
-                profile_image_url: profile.profile_image_url,
-                user_account_id: user.user_account_id,
-                is_followed: match is_followed {
-                    None => false,
-                    Some(_) => true
-                }
+                profile_image_url,
+                user_account_id: follower_account_id,
+                is_followed,
             });
         }
-        follow_list
-
+        Page {
+            items: follow_list,
+            next_cursor,
+        }
     }
 
     // Get user followers count
@@ -234,378 +728,3237 @@ impl Contract {
             self.is_user_exists(user_account_id.clone()),
             "User does not exist!"
         );
-        self.user_followers
-            .iter()
-            .filter(|u| u.follower_account_id == user_account_id)
-            .count() as u64
+        self.followers
+            .get(&user_account_id)
+            .map_or(0, |followers| followers.len())
     }
 
-    // Create new post
-    pub fn create_post(&mut self, content: String) {
+    // Create new post, optionally quoting another post with `quoted_post_id`, with media `attachments`
+    // and with a `poll`, an attached deposit pays for its storage and is refunded when it is deleted
+    #[payable]
+    pub fn create_post(
+        &mut self,
+        content: String,
+        quoted_post_id: Option<u64>,
+        attachments: Option<Vec<post::Attachment>>,
+        poll: Option<post::NewPoll>,
+    ) {
         let user_address: AccountId = env::signer_account_id();
-        self.all_posts.push(&post::PostDetail {
-            post_id: self.post_counter + 1,
+        self.assert_registered(&user_address);
+        let initial_storage = env::storage_usage();
+        let post_id = self.post_counter + 1;
+        if let Some(attachments) = attachments.filter(|attachments| !attachments.is_empty()) {
+            assert_valid_attachments(&attachments);
+            self.post_attachments.insert(&post_id, &attachments);
+        }
+        if let Some(poll) = poll {
+            assert_valid_poll(&poll);
+            self.post_polls.insert(
+                &post_id,
+                &post::Poll {
+                    vote_counts: vec![0; poll.options.len()],
+                    options: poll.options,
+                    closes_at: poll.closes_at,
+                },
+            );
+        }
+        if let Some(quoted_post_id) = quoted_post_id {
+            self.assert_post_exists(quoted_post_id);
+            self.quoted_post_ids.insert(&post_id, &quoted_post_id);
+
+            let mut quotes = self.post_quotes_of(quoted_post_id);
+            quotes.insert(&post_id, &());
+            self.post_quotes.insert(&quoted_post_id, &quotes);
+        }
+        self.add_hashtag_usage(post_id, &extract_hashtags(&content));
+        let mentions = self.insert_mentions(&content, &user_address, post_id, None, &[]);
+        if !mentions.is_empty() {
+            self.post_mentions.insert(&post_id, &mentions);
+        }
+        self.insert_post(&post::PostDetail {
+            post_id,
             user_address,
             content,
             created_at: env::block_timestamp(),
         });
-        self.post_counter = self.post_counter + 1;
+        self.post_counter += 1;
+        self.charge_post_storage(post_id, initial_storage);
     }
 
-    // Like and unlike a post by its post ID
-    pub fn like_post(&mut self, post_id: u64) {
+    // Edit the content of a post, keeping the previous content as a revision
+    pub fn edit_post(&mut self, post_id: u64, content: String) {
         let address = env::signer_account_id();
-        let is_liked = self
-            .post_likes
+        let mut post = self.expect_post(post_id);
+        require!(
+            post.user_address == address,
+            "Only the author can edit this post!"
+        );
+
+        let old_hashtags = extract_hashtags(&post.content);
+        let mut revisions = self.post_revisions_of(post_id);
+        let now = env::block_timestamp();
+        let created_at = revisions
+            .len()
+            .checked_sub(1)
+            .and_then(|last| revisions.get(last))
+            .map_or(post.created_at, |revision| revision.replaced_at);
+        revisions.push(&post::PostRevision {
+            content: post.content,
+            created_at,
+            replaced_at: now,
+        });
+        self.post_revisions.insert(&post_id, &revisions);
+
+        // Only hashtags the edit dropped or introduced are re-indexed
+        let new_hashtags = extract_hashtags(&content);
+        let removed: Vec<String> = old_hashtags
+            .iter()
+            .filter(|tag| !new_hashtags.contains(tag))
+            .cloned()
+            .collect();
+        let added: Vec<String> = new_hashtags
             .iter()
-            .filter(|pl| pl.post_id == post_id)
-            .find(|pl| pl.user_address == address);
+            .filter(|tag| !old_hashtags.contains(tag))
+            .cloned()
+            .collect();
+        self.unindex_hashtags(post_id, &removed);
+        self.remove_hashtag_usage(post_id, &removed);
+        self.index_hashtags(post_id, &added);
+        self.add_hashtag_usage(post_id, &added);
 
-        if is_liked.is_none() {
-            self.post_likes.push(&post::PostLikes {
-                post_id,
-                user_address: address,
-                created_at: env::block_timestamp(),
-            })
+        let previous = self.post_mentions.get(&post_id).unwrap_or_default();
+        let mentions = self.update_mentions(previous, &content, &address, post_id, None);
+        store_unless_empty(
+            &mut self.post_mentions,
+            post_id,
+            &mentions,
+            mentions.is_empty(),
+        );
+
+        post.content = content;
+        self.posts.insert(&post_id, &post);
+    }
+
+    // Retrieve the previous contents of a post, oldest first
+    pub fn get_post_revisions(&self, post_id: u64) -> Vec<post::PostRevision> {
+        self.assert_post_exists(post_id);
+        self.post_revisions_of(post_id).to_vec()
+    }
+
+    // Delete a post, callable by the author or a moderator,
+    // its likes, comments and other records are removed in batches by `clear_deleted_posts`
+    pub fn delete_post(&mut self, post_id: u64) {
+        let address = env::signer_account_id();
+        let post = self.expect_post(post_id);
+        require!(
+            post.user_address == address || self.moderators.contains(&address),
+            "Only the author or a moderator can delete this post!"
+        );
+
+        let initial_storage = env::storage_usage();
+        let deposit = self.post_storage_deposits.remove(&post_id);
+        let payer = self.post_storage_payers.remove(&post_id);
+        self.remove_post(&post);
+        self.deleted_post_ids.push(&post_id);
+        let freed = Balance::from(initial_storage.saturating_sub(env::storage_usage()))
+            * env::storage_byte_cost();
+
+        // Only storage that was paid for is refunded, the rest was covered by the contract
+        let refund = freed.min(deposit.unwrap_or(0));
+        if refund > 0 {
+            Promise::new(payer.unwrap_or(post.user_address)).transfer(refund);
+        }
+    }
+
+    // Like and unlike a post by its post ID, a like replaces any other reaction on the post
+    pub fn like_post(&mut self, post_id: u64) {
+        let address = env::signer_account_id();
+        let kind = if self.is_post_liked(post_id, &address) {
+            None
         } else {
-            let index = self
-                .post_likes
-                .iter()
-                .position(|pl| pl.post_id == post_id && pl.user_address == address)
-                .unwrap() as u64;
-            self.post_likes.swap_remove(index);
+            Some(DEFAULT_REACTION.to_string())
+        };
+        self.react_to_post(post_id, kind);
+    }
+
+    // Set the signer's reaction to a post, replacing their previous one, `None` removes it
+    pub fn react_to_post(&mut self, post_id: u64, kind: Option<String>) {
+        require!(
+            self.legacy_post_likes.is_empty(),
+            "Post likes migration in progress!"
+        );
+        let address = env::signer_account_id();
+        self.assert_registered(&address);
+        self.assert_post_exists(post_id);
+        if let Some(kind) = &kind {
+            require!(
+                self.reaction_kinds.contains(kind),
+                "Reaction kind does not exist!"
+            );
+        }
+
+        self.remove_post_like(post_id, &address);
+        let mut reactions = self.post_reactions_of(post_id);
+        let mut counts = self.reaction_counts.get(&post_id).unwrap_or_default();
+        let previous_kind = reactions.remove(&address);
+        let mut reactions_changed = previous_kind.is_some();
+        if let Some(previous_kind) = previous_kind {
+            match counts.get(&previous_kind).copied().unwrap_or(0) {
+                0 | 1 => counts.remove(&previous_kind),
+                count => counts.insert(previous_kind, count - 1),
+            };
+        }
+        match kind {
+            Some(kind) if kind == DEFAULT_REACTION => {
+                self.like_counter += 1;
+                self.insert_post_like(
+                    self.like_counter,
+                    &post::PostLikes {
+                        post_id,
+                        user_address: address,
+                        created_at: env::block_timestamp(),
+                    },
+                );
+            }
+            Some(kind) => {
+                reactions.insert(&address, &kind);
+                *counts.entry(kind).or_insert(0) += 1;
+                reactions_changed = true;
+            }
+            None => {}
+        }
+
+        // Likes live in the like index, so a post only has a reactions record while
+        // someone holds another kind of reaction on it
+        if reactions_changed {
+            if reactions.is_empty() {
+                self.post_reactions.remove(&post_id);
+                self.reaction_counts.remove(&post_id);
+            } else {
+                self.post_reactions.insert(&post_id, &reactions);
+                self.reaction_counts.insert(&post_id, &counts);
+            }
+        }
+    }
+
+    // Repost a post into the signer's timeline, returns false if it was already reposted
+    pub fn repost(&mut self, post_id: u64) -> bool {
+        let address = env::signer_account_id();
+        self.assert_registered(&address);
+        self.assert_post_exists(post_id);
+        if self.repost_ids.contains_key(&(post_id, address.clone())) {
+            return false;
+        }
+
+        self.post_counter += 1;
+        let repost = post::Repost {
+            repost_id: self.post_counter,
+            post_id,
+            user_address: address,
+            created_at: env::block_timestamp(),
+        };
+        self.reposts.insert(&repost.repost_id, &repost);
+        self.repost_ids
+            .insert(&(post_id, repost.user_address.clone()), &repost.repost_id);
+
+        let mut reposters = self.post_reposts_of(post_id);
+        reposters.insert(&repost.repost_id, &repost.user_address);
+        self.post_reposts.insert(&post_id, &reposters);
+
+        let mut post_ids = self.user_posts_of(&repost.user_address);
+        post_ids.insert(&repost.repost_id, &());
+        self.user_posts.insert(&repost.user_address, &post_ids);
+        true
+    }
+
+    // Remove the signer's repost of a post, returns false if it was not reposted
+    pub fn undo_repost(&mut self, post_id: u64) -> bool {
+        let address = env::signer_account_id();
+        match self.repost_ids.get(&(post_id, address)) {
+            None => false,
+            Some(repost_id) => {
+                self.remove_repost(repost_id);
+                true
+            }
         }
     }
 
-    // Comment on a post
-    pub fn comment_on_post(&mut self, post_id: u64, comment: String) {
+    // Vote for an option of a post's poll, each account votes once before the poll closes
+    pub fn vote_poll(&mut self, post_id: u64, option_index: u64) {
+        let address = env::signer_account_id();
+        self.assert_registered(&address);
+        self.assert_post_exists(post_id);
+        let poll = self.post_polls.get(&post_id);
+        require!(poll.is_some(), "Post has no poll!");
+        let mut poll = poll.unwrap();
+        require!(env::block_timestamp() < poll.closes_at, "Poll is closed!");
+        require!(
+            option_index < poll.options.len() as u64,
+            "Poll option does not exist!"
+        );
+
+        let mut votes = self
+            .poll_votes
+            .get(&post_id)
+            .unwrap_or_else(|| UnorderedMap::new(StorageKeys::PollVotesOf { post_id }));
+        require!(votes.get(&address).is_none(), "Already voted in this poll!");
+        votes.insert(&address, &option_index);
+        self.poll_votes.insert(&post_id, &votes);
+
+        poll.vote_counts[option_index as usize] += 1;
+        self.post_polls.insert(&post_id, &poll);
+    }
+
+    // Comment on a post with optional media `attachments`,
+    // or reply to one of its comments with `parent_comment_id`
+    pub fn comment_on_post(
+        &mut self,
+        post_id: u64,
+        comment: String,
+        parent_comment_id: Option<u64>,
+        attachments: Option<Vec<post::Attachment>>,
+    ) {
         require!(comment.chars().count() > 0, "Comment cannot be empty!");
 
         let address = env::signer_account_id();
+        self.assert_registered(&address);
+        self.assert_post_exists(post_id);
+
+        let comment_id = self.comment_counter + 1;
+        if let Some(parent_comment_id) = parent_comment_id {
+            require!(
+                self.expect_comment(parent_comment_id).post_id == post_id,
+                "Parent comment belongs to another post!"
+            );
+            self.comment_parents.insert(&comment_id, &parent_comment_id);
+
+            let mut replies = self.comment_replies_of(parent_comment_id);
+            replies.insert(&comment_id, &());
+            self.comment_replies.insert(&parent_comment_id, &replies);
+        }
 
-        self.post_comments.push(&post::PostComment {
-            comment_id: self.comment_counter + 1,
+        if let Some(attachments) = attachments.filter(|attachments| !attachments.is_empty()) {
+            assert_valid_attachments(&attachments);
+            self.comment_attachments.insert(&comment_id, &attachments);
+        }
+        let mentions = self.insert_mentions(&comment, &address, post_id, Some(comment_id), &[]);
+        if !mentions.is_empty() {
+            self.comment_mentions.insert(&comment_id, &mentions);
+        }
+        self.insert_comment(&post::PostComment {
+            comment_id,
             post_id,
             user_address: address,
             comment,
             created_at: env::block_timestamp(),
         });
 
-        self.comment_counter = self.comment_counter + 1;
+        self.comment_counter += 1;
     }
 
-    // Retrieve post comments details
-    pub fn get_post_comment_details(&self, post_id: u64) -> Vec<post::PostCommentDetailsOutput> {
-        let mut post_comments: Vec<post::PostCommentDetailsOutput> = vec![];
-        for (_, post_comment) in self
-            .post_comments
-            .iter()
-            .filter(|pc| pc.post_id == post_id)
-            .enumerate()
-        {
-            let profile_image_url = self
-                .get_account_details(post_comment.user_address.clone())
-                .unwrap()
-                .profile_image_url;
-            post_comments.push(post::PostCommentDetailsOutput {
-                comment_id: post_comment.comment_id,
-                user_address: post_comment.user_address,
-                profile_image_url,
-                comment: post_comment.comment,
-                created_at: post_comment.created_at,
-            })
+    // Edit the text of a comment
+    pub fn edit_comment(&mut self, comment_id: u64, comment: String) {
+        require!(comment.chars().count() > 0, "Comment cannot be empty!");
+        let address = env::signer_account_id();
+        let mut post_comment = self.expect_comment(comment_id);
+        self.assert_post_exists(post_comment.post_id);
+        require!(
+            post_comment.user_address == address,
+            "Only the commenter can edit this comment!"
+        );
+
+        let previous = self.comment_mentions.get(&comment_id).unwrap_or_default();
+        let mentions = self.update_mentions(
+            previous,
+            &comment,
+            &address,
+            post_comment.post_id,
+            Some(comment_id),
+        );
+        store_unless_empty(
+            &mut self.comment_mentions,
+            comment_id,
+            &mentions,
+            mentions.is_empty(),
+        );
+
+        post_comment.comment = comment;
+        self.comments.insert(&comment_id, &post_comment);
+        self.comment_edited_at
+            .insert(&comment_id, &env::block_timestamp());
+    }
+
+    // Delete a comment, callable by the commenter or the post author
+    pub fn delete_comment(&mut self, comment_id: u64) {
+        let address = env::signer_account_id();
+        let post_comment = self.expect_comment(comment_id);
+        let is_post_author = self
+            .posts
+            .get(&post_comment.post_id)
+            .is_some_and(|post| post.user_address == address);
+        require!(
+            post_comment.user_address == address || is_post_author,
+            "Only the commenter or the post author can delete this comment!"
+        );
+
+        if let Some(parent_comment_id) = self.comment_parents.get(&comment_id) {
+            let mut replies = self.comment_replies_of(parent_comment_id);
+            replies.remove(&comment_id);
+            store_unless_empty(
+                &mut self.comment_replies,
+                parent_comment_id,
+                &replies,
+                replies.is_empty(),
+            );
+        }
+        // Replies and likes go along with the comment, they are removed by `clear_deleted_comments`
+        self.detach_comment(comment_id);
+    }
+
+    // Remove up to `limit` replies and likes left behind by deleted comments, returns how many
+    // deleted comments are left
+    #[private]
+    pub fn clear_deleted_comments(&mut self, limit: u64) -> u64 {
+        let mut budget = limit;
+        while budget > 0 {
+            let comment_id = match self
+                .deleted_comment_ids
+                .len()
+                .checked_sub(1)
+                .and_then(|last| self.deleted_comment_ids.get(last))
+            {
+                None => break,
+                Some(comment_id) => comment_id,
+            };
+
+            // Replies are queued in turn, so the deepest ones are cleared first
+            let mut replies = self.comment_replies_of(comment_id);
+            if let Some(reply_id) = replies.min() {
+                replies.remove(&reply_id);
+                store_unless_empty(
+                    &mut self.comment_replies,
+                    comment_id,
+                    &replies,
+                    replies.is_empty(),
+                );
+                self.detach_comment(reply_id);
+                budget -= 1;
+                continue;
+            }
+
+            let mut likers = self.comment_likers_of(comment_id);
+            let accounts: Vec<AccountId> = likers.iter().take(budget as usize).collect();
+            for account in accounts {
+                likers.remove(&account);
+                budget -= 1;
+            }
+            store_unless_empty(
+                &mut self.comment_likers,
+                comment_id,
+                &likers,
+                likers.is_empty(),
+            );
+            if budget == 0 {
+                break;
+            }
+
+            self.deleted_comment_ids.pop();
+            self.remove_comment_data(comment_id);
+            budget -= 1;
+        }
+        self.deleted_comment_ids.len()
+    }
+
+    // Retrieve a comment with its replies nested up to `max_depth` levels deep
+    pub fn get_comment_thread(
+        &self,
+        comment_id: u64,
+        max_depth: Option<u64>,
+        perspective: Option<AccountId>,
+    ) -> post::CommentThread {
+        let max_depth = max_depth.unwrap_or(DEFAULT_THREAD_DEPTH);
+        require!(max_depth <= MAX_THREAD_DEPTH, "Depth must be at most 5!");
+        let mut budget = MAX_THREAD_REPLIES;
+        self.comment_thread(
+            self.expect_comment(comment_id),
+            max_depth,
+            &mut budget,
+            perspective.as_ref(),
+        )
+    }
+
+    // Retrieve a page of the direct replies to a comment, oldest first
+    pub fn get_comment_replies(
+        &self,
+        comment_id: u64,
+        cursor: Option<u64>,
+        limit: Option<u64>,
+        perspective: Option<AccountId>,
+    ) -> Page<post::PostCommentDetailsOutput> {
+        self.expect_comment(comment_id);
+        let (reply_ids, next_cursor) =
+            paginate(&self.comment_replies_of(comment_id), cursor, limit, false);
+        let items = reply_ids
+            .into_iter()
+            .filter_map(|(reply_id, _)| self.comments.get(&reply_id))
+            .map(|reply| self.comment_output(reply, perspective.as_ref()))
+            .collect();
+        Page { items, next_cursor }
+    }
+
+    // Like and unlike a comment by its comment ID
+    pub fn like_comment(&mut self, comment_id: u64) {
+        let address = env::signer_account_id();
+        self.assert_registered(&address);
+        let comment = self.expect_comment(comment_id);
+        self.assert_post_exists(comment.post_id);
+
+        let mut likers = self.comment_likers_of(comment_id);
+        let previous_count = likers.len();
+        if !likers.remove(&address) {
+            likers.insert(&address);
+        }
+        self.comment_likers.insert(&comment_id, &likers);
+
+        let mut ranking = self.post_top_comments_of(comment.post_id);
+        ranking.remove(&(previous_count, comment_id));
+        ranking.insert(&(likers.len(), comment_id), &());
+        self.post_top_comments.insert(&comment.post_id, &ranking);
+    }
+
+    // Retrieve post comments details, starting after the `cursor` comment in `sort` order,
+    // for `top_liked` the cursor is the number of comments already fetched
+    pub fn get_post_comment_details(
+        &self,
+        post_id: u64,
+        cursor: Option<u64>,
+        limit: Option<u64>,
+        sort: Option<post::CommentSortOrder>,
+        perspective: Option<AccountId>,
+    ) -> Page<post::PostCommentDetailsOutput> {
+        let (comments, next_cursor) = self.comments_page(post_id, cursor, limit, sort);
+        Page {
+            items: comments
+                .into_iter()
+                .map(|comment| self.comment_output(comment, perspective.as_ref()))
+                .collect(),
+            next_cursor,
         }
-        post_comments
     }
 
-    // Retrieve post likes details
-    pub fn get_post_likes_details(&self, post_id: u64) -> Vec<post::PostLikeDetailsOutput> {
+    // Retrieve post likes details, starting after the `cursor` like in `sort` order
+    pub fn get_post_likes_details(
+        &self,
+        post_id: u64,
+        cursor: Option<u64>,
+        limit: Option<u64>,
+        sort: Option<post::SortOrder>,
+    ) -> Page<post::PostLikeDetailsOutput> {
+        let (likes, next_cursor) = self.likes_page(post_id, cursor, limit, sort);
         let mut post_likes: Vec<post::PostLikeDetailsOutput> = vec![];
-        for (_, post_like) in self
-            .post_likes
-            .iter()
-            .filter(|pl| pl.post_id == post_id)
-            .enumerate()
-        {
-            let profile_image_url = self
-                .get_account_details(post_like.user_address.clone())
-                .unwrap()
-                .profile_image_url;
+        for post_like in likes {
+            let profile_image_url = self.profile_of(&post_like.user_address).profile_image_url;
             post_likes.push(post::PostLikeDetailsOutput {
-                    user_address: post_like.user_address,
+                user_address: post_like.user_address,
                 profile_image_url,
             })
         }
-        post_likes
+        Page {
+            items: post_likes,
+            next_cursor,
+        }
     }
 
     // Get poster address based on post ID
     pub fn get_poster_address(&self, post_id: u64) -> AccountId {
-        self.all_posts
-            .iter()
-            .find(|p| p.post_id == post_id)
-            .unwrap()
-            .user_address
-    }
-
-    // Retrieve all available posts
-    pub fn get_all_posts(&self, account_id: Option<AccountId>) -> Vec<post::PostOutputFormat> {
-        let mut posts: Vec<post::PostOutputFormat> = vec![];
-        for (_, post) in (self.all_posts.iter().enumerate()).rev() {
-            let profile = self
-                .get_account_details(self.get_poster_address(post.post_id))
-                .unwrap();
-            let like_count = self
-                .post_likes
-                .iter()
-                .filter(|p| p.post_id == post.post_id)
-                .count() as u64;
-            let comment_count = self
-                .post_comments
-                .iter()
-                .filter(|p| p.post_id == post.post_id)
-                .count() as u64;
-            let mut is_liked = false;
-
-            if account_id != None {
-                is_liked = self
-                    .post_likes
-                    .iter()
-                    .filter(|p| p.post_id == post.post_id)
-                    .any(|p| p.user_address == account_id.clone().unwrap());
-            }
-
-            posts.push(post::PostOutputFormat {
-                name: profile.name,
-                profile_image_url: profile.profile_image_url,
-                post,
-                like_count,
-                comment_count,
-                like_details: None,
-                comment_details: None,
-                is_liked: if account_id != None {
-                    Some(is_liked)
-                } else {
-                    None
-                },
-            })
-        }
-        posts
+        self.expect_post(post_id).user_address
     }
 
-    // Retrieve single post detail
-    pub fn get_single_post(
+    // Find a post by its post ID
+    pub fn get_post(&self, post_id: u64) -> Option<post::PostDetail> {
+        self.posts.get(&post_id)
+    }
+
+    // Retrieve all available posts, newest first, starting below `before_post_id`
+    pub fn get_all_posts(
         &self,
-        post_id: u64,
         account_id: Option<AccountId>,
+        before_post_id: Option<u64>,
+        limit: Option<u64>,
+    ) -> Page<post::PostOutputFormat> {
+        let (posts, next_cursor) = paginate(&self.posts, before_post_id, limit, true);
+        Page {
+            items: posts
+                .into_iter()
+                .map(|(_, post)| self.post_output(post, account_id.as_ref()))
+                .collect(),
+            next_cursor,
+        }
+    }
+
+    // Retrieve single post detail with its first `comment_limit` comments and first page of likes,
+    // a `comment_limit` of 0 leaves the comments out
+    pub fn get_single_post(
+        &self,
+        post_id: u64,
+        account_id: Option<AccountId>,
+        comment_limit: Option<u64>,
     ) -> post::PostOutputFormat {
+        let post = self.expect_post(post_id);
+        let (like_details, _) = self.likes_page(post_id, None, None, None);
+        let comment_details = match comment_limit {
+            Some(0) => vec![],
+            _ => self.comments_page(post_id, None, comment_limit, None).0,
+        };
+
+        let mut output = self.post_output(post, account_id.as_ref());
+        output.like_details = Some(like_details);
+        output.comment_details = Some(comment_details);
+        output
+    }
+
+    // Retrieve the posts tagged with `tag`, newest first, starting below `before_post_id`
+    pub fn get_posts_by_hashtag(
+        &self,
+        tag: String,
+        account_id: Option<AccountId>,
+        before_post_id: Option<u64>,
+        limit: Option<u64>,
+    ) -> Page<post::PostOutputFormat> {
+        let tag = tag.trim_start_matches('#').to_lowercase();
+        let (post_ids, next_cursor) =
+            paginate(&self.hashtag_posts_of(&tag), before_post_id, limit, true);
+        Page {
+            items: post_ids
+                .into_iter()
+                .filter_map(|(post_id, _)| self.posts.get(&post_id))
+                .map(|post| self.post_output(post, account_id.as_ref()))
+                .collect(),
+            next_cursor,
+        }
+    }
+
+    // Retrieve the hashtags most used by posts in the last `window_hours` hours, most used first
+    pub fn get_trending_hashtags(
+        &self,
+        window_hours: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<post::HashtagCount> {
+        let window_hours = window_hours.unwrap_or(DEFAULT_TRENDING_WINDOW_HOURS);
         require!(
-            self.all_posts
-                .iter()
-                .find(|p| p.post_id == post_id)
-                .is_some(),
-            "Post does not exist!"
+            window_hours > 0 && window_hours <= MAX_TRENDING_WINDOW_HOURS,
+            "Window must be between 1 and 168 hours!"
         );
-        let profile = self
-            .get_account_details(self.get_poster_address(post_id))
-            .unwrap();
-        let post = self
-            .all_posts
-            .iter()
-            .find(|p| p.post_id == post_id)
-            .unwrap();
-        let like_details = self
-            .post_likes
-            .iter()
-            .filter(|p| p.post_id == post_id)
-            .collect::<Vec<post::PostLikes>>();
-        let comment_details = self
-            .post_comments
-            .iter()
-            .filter(|p| p.post_id == post_id)
-            .collect::<Vec<post::PostComment>>();
-        let mut is_liked = false;
+        let limit = page_limit(Some(limit.unwrap_or(DEFAULT_TRENDING_LIMIT)));
 
-        if account_id != None {
-            is_liked = self
-                .post_likes
-                .iter()
-                .filter(|p| p.post_id == post.post_id)
-                .any(|p| p.user_address == account_id.clone().unwrap());
+        let current_hour = env::block_timestamp() / NANOSECONDS_PER_HOUR;
+        let mut counts: BTreeMap<String, u64> = BTreeMap::new();
+        let first_hour = current_hour.saturating_sub(window_hours - 1);
+        for (_, usage) in self
+            .hashtag_usage
+            .range((Bound::Included(first_hour), Bound::Unbounded))
+        {
+            for (tag, count) in usage.iter() {
+                *counts.entry(tag).or_insert(0) += count;
+            }
+        }
+        let mut trending: Vec<post::HashtagCount> = counts
+            .into_iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(tag, count)| post::HashtagCount { tag, count })
+            .collect();
+        trending.sort_by_key(|hashtag| Reverse(hashtag.count));
+        trending.truncate(limit as usize);
+        trending
+    }
+
+    // Retrieve the posts and comments mentioning an account, newest first,
+    // starting below `before_mention_id`
+    pub fn get_mentions(
+        &self,
+        account_id: AccountId,
+        before_mention_id: Option<u64>,
+        limit: Option<u64>,
+    ) -> Page<post::Mention> {
+        let (mentions, next_cursor) = paginate(
+            &self.mentions_of(&account_id),
+            before_mention_id,
+            limit,
+            true,
+        );
+        Page {
+            items: mentions.into_iter().map(|(_, mention)| mention).collect(),
+            next_cursor,
+        }
+    }
+
+    // Retrieve the posts quoting a post, newest first, starting below `before_post_id`
+    pub fn get_post_quotes(
+        &self,
+        post_id: u64,
+        account_id: Option<AccountId>,
+        before_post_id: Option<u64>,
+        limit: Option<u64>,
+    ) -> Page<post::PostOutputFormat> {
+        let (post_ids, next_cursor) =
+            paginate(&self.post_quotes_of(post_id), before_post_id, limit, true);
+        Page {
+            items: post_ids
+                .into_iter()
+                .filter_map(|(post_id, _)| self.posts.get(&post_id))
+                .map(|post| self.post_output(post, account_id.as_ref()))
+                .collect(),
+            next_cursor,
+        }
+    }
+
+    // Get specific user posts and reposts, newest first, starting below `before_post_id`,
+    // reposts are shown with the original author's profile
+    pub fn get_user_posts(
+        &self,
+        account_id: AccountId,
+        perspective: Option<AccountId>,
+        before_post_id: Option<u64>,
+        limit: Option<u64>,
+    ) -> Page<post::PostOutputFormat> {
+        let (post_ids, next_cursor) = paginate(
+            &self.user_posts_of(&account_id),
+            before_post_id,
+            limit,
+            true,
+        );
+        let viewer = perspective.unwrap_or(account_id);
+        Page {
+            items: post_ids
+                .into_iter()
+                .filter_map(|(post_id, _)| self.timeline_entry(post_id, &viewer))
+                .collect(),
+            next_cursor,
         }
+    }
+}
 
+// Internal helpers
+impl Contract {
+    // Feed entry for a post, `is_liked` is only filled in when there is a viewer
+    fn post_output(
+        &self,
+        post: post::PostDetail,
+        viewer: Option<&AccountId>,
+    ) -> post::PostOutputFormat {
+        let profile = self.profile_of(&post.user_address);
+        let revisions = self.post_revisions.get(&post.post_id);
         post::PostOutputFormat {
             name: profile.name,
             profile_image_url: profile.profile_image_url,
+            like_count: self.post_like_count(post.post_id),
+            comment_count: self.post_comment_count(post.post_id),
+            like_details: None,
+            comment_details: None,
+            is_liked: viewer.map(|account| self.is_post_liked(post.post_id, account)),
+            reaction_counts: self.post_reaction_counts(post.post_id),
+            reaction: viewer.and_then(|account| self.post_reaction(post.post_id, account)),
+            repost_count: self
+                .post_reposts
+                .get(&post.post_id)
+                .map_or(0, |reposters| reposters.len()),
+            is_reposted: viewer.map(|account| {
+                self.repost_ids
+                    .contains_key(&(post.post_id, account.clone()))
+            }),
+            repost: None,
+            mentions: self.mentioned_accounts(self.post_mentions.get(&post.post_id)),
+            attachments: self.post_attachments.get(&post.post_id).unwrap_or_default(),
+            poll: self
+                .post_polls
+                .get(&post.post_id)
+                .map(|poll| self.poll_output(post.post_id, poll, viewer)),
+            quoted_post: self
+                .quoted_post_ids
+                .get(&post.post_id)
+                .map(|quoted_post_id| self.quoted_post(quoted_post_id)),
+            quote_count: self
+                .post_quotes
+                .get(&post.post_id)
+                .map_or(0, |quotes| quotes.len()),
+            edited_at: revisions
+                .as_ref()
+                .and_then(|revisions| {
+                    revisions
+                        .len()
+                        .checked_sub(1)
+                        .and_then(|last| revisions.get(last))
+                })
+                .map(|revision| revision.replaced_at),
+            revision_count: revisions.map_or(0, |revisions| revisions.len()),
             post,
-            like_count: like_details.iter().count() as u64,
-            comment_count: comment_details.iter().count() as u64,
-            like_details: Some(like_details),
-            comment_details: Some(comment_details),
-            is_liked: if account_id != None {
-                Some(is_liked)
-            } else {
-                None
-            },
         }
     }
 
-    // Get specific user posts
-    pub fn get_user_posts(
+    // Poll results, `viewer_vote` is the option the viewer voted for if there is a viewer
+    fn poll_output(
         &self,
-        account_id: AccountId,
-        perspective: Option<AccountId>,
-    ) -> Vec<post::PostOutputFormat> {
-        let mut posts: Vec<post::PostOutputFormat> = vec![];
-        for (_, post) in self
-            .all_posts
-            .iter()
-            .filter(|p| p.user_address == account_id.clone())
-            .enumerate()
-        {
-            let profile = self
-                .get_account_details(self.get_poster_address(post.post_id))
-                .unwrap();
-            let like_count = self
-                .post_likes
-                .iter()
-                .filter(|p| p.post_id == post.post_id)
-                .count() as u64;
-            let comment_count = self
-                .post_comments
-                .iter()
-                .filter(|p| p.post_id == post.post_id)
-                .count() as u64;
-            let is_liked;
-            match perspective.clone() {
-                None => {
-                    is_liked = self
-                        .post_likes
-                        .iter()
-                        .filter(|p| p.post_id == post.post_id)
-                        .any(|p| p.user_address == account_id.clone());
-                }
-                Some(account) => {
-                    is_liked = self
-                        .post_likes
-                        .iter()
-                        .filter(|p| p.post_id == post.post_id)
-                        .any(|p| p.user_address == account);
+        post_id: u64,
+        poll: post::Poll,
+        viewer: Option<&AccountId>,
+    ) -> post::PollOutput {
+        let viewer_vote = viewer.and_then(|account| {
+            self.poll_votes
+                .get(&post_id)
+                .and_then(|votes| votes.get(account))
+        });
+        post::PollOutput {
+            closed: env::block_timestamp() >= poll.closes_at,
+            has_voted: viewer.map(|_| viewer_vote.is_some()),
+            viewer_vote,
+            options: poll.options,
+            vote_counts: poll.vote_counts,
+            closes_at: poll.closes_at,
+        }
+    }
+
+    // Inline summary of a quoted post, which may have been deleted since it was quoted
+    fn quoted_post(&self, post_id: u64) -> post::QuotedPost {
+        match self.posts.get(&post_id) {
+            None => post::QuotedPost {
+                post_id,
+                available: false,
+                name: None,
+                profile_image_url: None,
+                post: None,
+            },
+            Some(post) => {
+                let profile = self.profile_of(&post.user_address);
+                post::QuotedPost {
+                    post_id,
+                    available: true,
+                    name: Some(profile.name),
+                    profile_image_url: Some(profile.profile_image_url),
+                    post: Some(post),
                 }
             }
+        }
+    }
 
-            posts.push(post::PostOutputFormat {
-                name: profile.name,
-                profile_image_url: profile.profile_image_url,
-                post,
-                like_count,
-                comment_count,
-                like_details: None,
-                comment_details: None,
-                is_liked: Some(is_liked),
-            })
+    // A post or repost in a user's timeline, `None` if the post it refers to is gone
+    fn timeline_entry(&self, post_id: u64, viewer: &AccountId) -> Option<post::PostOutputFormat> {
+        if let Some(post) = self.posts.get(&post_id) {
+            return Some(self.post_output(post, Some(viewer)));
         }
-        posts
+        let repost = self.reposts.get(&post_id)?;
+        let post = self.posts.get(&repost.post_id)?;
+        let mut output = self.post_output(post, Some(viewer));
+        output.repost = Some(repost);
+        Some(output)
     }
-}
 
-/*
- * the rest of this file sets up unit tests
- * to run these, the command will be:
- * cargo test --package rust-template -- --nocapture
- * Note: 'rust-template' comes from Cargo.toml's 'name' key
- */
+    // Record that `account_id` follows `followed_account_id` in both directions
+    fn insert_follow_edge(
+        &mut self,
+        follow_id: u64,
+        account_id: &AccountId,
+        followed_account_id: &AccountId,
+    ) {
+        self.follow_edges.insert(
+            &(account_id.clone(), followed_account_id.clone()),
+            &follow_id,
+        );
 
-// use the attribute below for unit tests
-#[cfg(test)]
-mod tests {
-    use near_sdk::{testing_env, VMContext};
+        let mut following = self.following_of(account_id);
+        following.insert(&follow_id, followed_account_id);
+        self.following.insert(account_id, &following);
 
-    use super::*;
+        let mut followers = self.followers_of(followed_account_id);
+        followers.insert(&follow_id, account_id);
+        self.followers.insert(followed_account_id, &followers);
 
-    // part of writing unit tests is setting up a mock context
-    // in this example, this is only needed for env::log in the contract
-    // this is also a useful list to peek at when wondering what's available in env::*
-    fn get_context(input: Vec<u8>) -> VMContext {
-        VMContext {
-            current_account_id: "alice.testnet".to_string().parse().unwrap(),
-            signer_account_id: "robert.testnet".to_string().parse().unwrap(),
-            signer_account_pk: vec![0, 1, 2],
-            predecessor_account_id: "jane.testnet".to_string().parse().unwrap(),
-            input,
-            block_index: 0,
-            block_timestamp: 0,
-            account_balance: 0,
-            account_locked_balance: 0,
-            storage_usage: 0,
-            attached_deposit: 0,
-            prepaid_gas: 10u64.pow(18),
-            random_seed: vec![0, 1, 2],
-            output_data_receivers: vec![],
-            epoch_height: 19,
-            view_config: None,
-        }
+        self.update_account(account_id, |account| account.following_count += 1);
+        self.update_account(followed_account_id, |account| account.followers_count += 1);
     }
 
-    #[test]
-    #[should_panic]
-    fn test_create_account() {
-        let ctx = get_context(vec![]);
-        testing_env!(ctx);
+    // Remove the follow edge from `account_id` to `followed_account_id` in both directions
+    fn remove_follow_edge(&mut self, account_id: &AccountId, followed_account_id: &AccountId) {
+        let follow_id = match self
+            .follow_edges
+            .remove(&(account_id.clone(), followed_account_id.clone()))
+        {
+            None => return,
+            Some(follow_id) => follow_id,
+        };
 
-        let mut contract = Contract::new();
-        println!(
-            "[ACCOUNT NOT CREATED]: check_user_exist value is [{}]",
-            contract.is_user_exists("robert.testnet".to_string().parse().unwrap())
-        );
+        let mut following = self.following_of(account_id);
+        following.remove(&follow_id);
+        self.following.insert(account_id, &following);
 
-        contract.create_account(
-            Some("Robert".into()),
-            Some("".into()),
-            Some("".into()),
-            Some("".into()),
-            Some("".into()),
-        );
-        println!(
-            "[ACCOUNT CREATED]: check_user_exist value is [{}]",
-            contract.is_user_exists("robert.testnet".to_string().parse().unwrap())
-        );
+        let mut followers = self.followers_of(followed_account_id);
+        followers.remove(&follow_id);
+        self.followers.insert(followed_account_id, &followers);
 
-        contract.create_account(
-            Some("Robert2".into()),
-            Some("".into()),
-            Some("".into()),
-            Some("".into()),
-            Some("".into()),
+        self.update_account(account_id, |account| {
+            account.following_count = account.following_count.saturating_sub(1)
+        });
+        self.update_account(followed_account_id, |account| {
+            account.followers_count = account.followers_count.saturating_sub(1)
+        });
+    }
+
+    fn assert_registered(&self, account_id: &AccountId) {
+        require!(
+            self.user_list.contains_key(account_id),
+            "Account does not exist!"
         );
     }
 
-    #[test]
-    fn test_change_profile_image() {
-        let ctx = get_context(vec![]);
-        testing_env!(ctx);
+    // Stored profile of an account, or an empty one for records left by unregistered accounts
+    fn profile_of(&self, account_id: &AccountId) -> user::UserAccountDetail {
+        self.user_list
+            .get(account_id)
+            .unwrap_or_else(|| user::UserAccountDetail {
+                address: account_id.to_string(),
+                name: "".into(),
+                profile_image_url: "".into(),
+                location: "".into(),
+                url: "".into(),
+                description: "".into(),
+                created_at: 0,
+                followers_count: 0,
+                following_count: 0,
+            })
+    }
 
-        let mut contract = Contract::new();
-        contract.create_account(
-            Some("Mond".into()),
-            Some("changethis".into()),
-            Some("".into()),
-            Some("".into()),
-            Some("".into()),
+    // Apply `update` to a registered account's stored details
+    fn update_account<F>(&mut self, account_id: &AccountId, update: F)
+    where
+        F: FnOnce(&mut user::UserAccountDetail),
+    {
+        if let Some(mut account) = self.user_list.get(account_id) {
+            update(&mut account);
+            self.user_list.insert(account_id, &account);
+        }
+    }
+
+    // Record a like in its post's like index
+    fn insert_post_like(&mut self, like_id: u64, like: &post::PostLikes) {
+        self.post_like_ids
+            .insert(&(like.post_id, like.user_address.clone()), &like_id);
+
+        let mut likers = self.post_likers_of(like.post_id);
+        likers.insert(&like_id, like);
+        self.post_likers.insert(&like.post_id, &likers);
+    }
+
+    // Remove `account_id`'s like from its post's like index
+    fn remove_post_like(&mut self, post_id: u64, account_id: &AccountId) {
+        let like_id = match self.post_like_ids.remove(&(post_id, account_id.clone())) {
+            None => return,
+            Some(like_id) => like_id,
+        };
+
+        let mut likers = self.post_likers_of(post_id);
+        likers.remove(&like_id);
+        self.post_likers.insert(&post_id, &likers);
+    }
+
+    fn is_post_liked(&self, post_id: u64, account_id: &AccountId) -> bool {
+        self.post_like_ids
+            .contains_key(&(post_id, account_id.clone()))
+    }
+
+    fn post_like_count(&self, post_id: u64) -> u64 {
+        self.post_likers
+            .get(&post_id)
+            .map_or(0, |likers| likers.len())
+    }
+
+    // A page of a post's likes in `sort` order, oldest first by default
+    fn likes_page(
+        &self,
+        post_id: u64,
+        cursor: Option<u64>,
+        limit: Option<u64>,
+        sort: Option<post::SortOrder>,
+    ) -> (Vec<post::PostLikes>, Option<u64>) {
+        let (likes, next_cursor) = paginate(
+            &self.post_likers_of(post_id),
+            cursor,
+            limit,
+            sort == Some(post::SortOrder::NewestFirst),
         );
+        (
+            likes.into_iter().map(|(_, like)| like).collect(),
+            next_cursor,
+        )
+    }
 
-        println!(
-            "prev image url: {:?}",
-            contract
-                .get_account_details("robert.testnet".to_string().parse().unwrap())
-                .unwrap()
-                .profile_image_url
+    // Number of reactions of each kind on a post, likes are counted as `DEFAULT_REACTION`
+    fn post_reaction_counts(&self, post_id: u64) -> BTreeMap<String, u64> {
+        let mut counts = self.reaction_counts.get(&post_id).unwrap_or_default();
+        let like_count = self.post_like_count(post_id);
+        if like_count > 0 {
+            counts.insert(DEFAULT_REACTION.to_string(), like_count);
+        }
+        counts
+    }
+
+    fn post_reaction(&self, post_id: u64, account_id: &AccountId) -> Option<String> {
+        if self.is_post_liked(post_id, account_id) {
+            return Some(DEFAULT_REACTION.to_string());
+        }
+        self.post_reactions
+            .get(&post_id)
+            .and_then(|reactions| reactions.get(account_id))
+    }
+
+    fn post_reactions_of(&self, post_id: u64) -> UnorderedMap<AccountId, String> {
+        self.post_reactions
+            .get(&post_id)
+            .unwrap_or_else(|| UnorderedMap::new(StorageKeys::PostReactionsOf { post_id }))
+    }
+
+    fn post_likers_of(&self, post_id: u64) -> TreeMap<u64, post::PostLikes> {
+        self.post_likers
+            .get(&post_id)
+            .unwrap_or_else(|| TreeMap::new(StorageKeys::PostLikersOf { post_id }))
+    }
+
+    // Store a post and add it to its author's post index and its hashtags' post indexes
+    fn insert_post(&mut self, post: &post::PostDetail) {
+        self.posts.insert(&post.post_id, post);
+        self.index_hashtags(post.post_id, &extract_hashtags(&post.content));
+
+        let mut post_ids = self.user_posts_of(&post.user_address);
+        post_ids.insert(&post.post_id, &());
+        self.user_posts.insert(&post.user_address, &post_ids);
+    }
+
+    // Remove a post from the post map, its author's post index and the other indexes it was
+    // added to on creation, the records others attached to it are left for `clear_post_records`
+    fn remove_post(&mut self, post: &post::PostDetail) {
+        self.posts.remove(&post.post_id);
+        let hashtags = extract_hashtags(&post.content);
+        self.unindex_hashtags(post.post_id, &hashtags);
+        self.remove_hashtag_usage(post.post_id, &hashtags);
+
+        let mut post_ids = self.user_posts_of(&post.user_address);
+        post_ids.remove(&post.post_id);
+        self.user_posts.insert(&post.user_address, &post_ids);
+
+        if let Some(mentions) = self.post_mentions.remove(&post.post_id) {
+            self.remove_mentions(&mentions);
+        }
+        self.post_attachments.remove(&post.post_id);
+        self.post_polls.remove(&post.post_id);
+        if let Some(quoted_post_id) = self.quoted_post_ids.remove(&post.post_id) {
+            if let Some(mut quotes) = self.post_quotes.get(&quoted_post_id) {
+                quotes.remove(&post.post_id);
+                self.post_quotes.insert(&quoted_post_id, &quotes);
+            }
+        }
+    }
+
+    // Keep the attached deposit for the storage a new post used and refund the rest,
+    // posts created without a deposit are paid for by the contract
+    fn charge_post_storage(&mut self, post_id: u64, initial_storage: u64) {
+        let deposit = env::attached_deposit();
+        if deposit == 0 {
+            return;
+        }
+        let cost = Balance::from(env::storage_usage().saturating_sub(initial_storage))
+            * env::storage_byte_cost();
+        require!(
+            deposit >= cost,
+            "Attached deposit does not cover the post storage!"
         );
+        let payer = env::predecessor_account_id();
+        self.post_storage_deposits.insert(&post_id, &cost);
+        self.post_storage_payers.insert(&post_id, &payer);
+        if deposit > cost {
+            Promise::new(payer).transfer(deposit - cost);
+        }
+    }
 
-        contract.edit_profile_image("changed!".into());
-        println!(
-            "after image url: {:?}",
-            contract.get_account_details("robert.testnet".to_string().parse().unwrap())
+    // Remove a repost from the reposter's timeline and its post's repost index
+    fn remove_repost(&mut self, repost_id: u64) {
+        let repost = match self.reposts.remove(&repost_id) {
+            None => return,
+            Some(repost) => repost,
+        };
+        self.repost_ids
+            .remove(&(repost.post_id, repost.user_address.clone()));
+
+        let mut reposters = self.post_reposts_of(repost.post_id);
+        reposters.remove(&repost_id);
+        self.post_reposts.insert(&repost.post_id, &reposters);
+
+        let mut post_ids = self.user_posts_of(&repost.user_address);
+        post_ids.remove(&repost_id);
+        self.user_posts.insert(&repost.user_address, &post_ids);
+    }
+
+    fn post_reposts_of(&self, post_id: u64) -> TreeMap<u64, AccountId> {
+        self.post_reposts
+            .get(&post_id)
+            .unwrap_or_else(|| TreeMap::new(StorageKeys::PostRepostsOf { post_id }))
+    }
+
+    fn index_hashtags(&mut self, post_id: u64, hashtags: &[String]) {
+        for tag in hashtags {
+            let mut post_ids = self.hashtag_posts_of(tag);
+            post_ids.insert(&post_id, &());
+            self.hashtag_posts.insert(tag, &post_ids);
+        }
+    }
+
+    fn hashtag_posts_of(&self, tag: &String) -> TreeMap<u64, ()> {
+        self.hashtag_posts.get(tag).unwrap_or_else(|| {
+            TreeMap::new(StorageKeys::HashtagPostsOf {
+                tag_hash: env::sha256(tag.as_bytes()),
+            })
+        })
+    }
+
+    fn unindex_hashtags(&mut self, post_id: u64, hashtags: &[String]) {
+        for tag in hashtags {
+            if let Some(mut post_ids) = self.hashtag_posts.get(tag) {
+                post_ids.remove(&post_id);
+                if post_ids.is_empty() {
+                    self.hashtag_posts.remove(tag);
+                } else {
+                    self.hashtag_posts.insert(tag, &post_ids);
+                }
+            }
+        }
+    }
+
+    // Count one use of each hashtag by a post in the current hour, remembering the hour
+    // so the use is taken back from the same bucket
+    fn add_hashtag_usage(&mut self, post_id: u64, hashtags: &[String]) {
+        let hour = env::block_timestamp() / NANOSECONDS_PER_HOUR;
+        self.prune_hashtag_usage(hour.saturating_sub(MAX_TRENDING_WINDOW_HOURS));
+        if hashtags.is_empty() {
+            return;
+        }
+
+        let mut usage = self
+            .hashtag_usage
+            .get(&hour)
+            .unwrap_or_else(|| UnorderedMap::new(StorageKeys::HashtagUsageOf { hour }));
+        let mut hashtag_hours = self.post_hashtag_hours.get(&post_id).unwrap_or_default();
+        for tag in hashtags {
+            usage.insert(tag, &(usage.get(tag).unwrap_or(0) + 1));
+            hashtag_hours.push((tag.clone(), hour));
+        }
+        self.hashtag_usage.insert(&hour, &usage);
+        self.post_hashtag_hours.insert(&post_id, &hashtag_hours);
+        self.hashtag_hour_posts.insert(&(hour, post_id), &());
+    }
+
+    // Drop up to `MAX_PRUNED_HASHTAG_ENTRIES` usage counts and post hours from before `oldest_hour`,
+    // which fell out of the longest trending window, so a busy hour is dropped over several calls
+    fn prune_hashtag_usage(&mut self, oldest_hour: u64) {
+        let mut budget = MAX_PRUNED_HASHTAG_ENTRIES;
+        while budget > 0 {
+            let hour = match self.hashtag_usage.min().filter(|hour| *hour < oldest_hour) {
+                None => break,
+                Some(hour) => hour,
+            };
+            let mut usage = self.hashtag_usage.get(&hour).unwrap();
+            let tags: Vec<String> = usage.keys().take(budget as usize).collect();
+            for tag in &tags {
+                usage.remove(tag);
+            }
+            budget -= (tags.len() as u64).max(1);
+            if usage.is_empty() {
+                self.hashtag_usage.remove(&hour);
+            } else {
+                self.hashtag_usage.insert(&hour, &usage);
+            }
+        }
+
+        while budget > 0 {
+            let (hour, post_id) = match self
+                .hashtag_hour_posts
+                .min()
+                .filter(|(hour, _)| *hour < oldest_hour)
+            {
+                None => break,
+                Some(key) => key,
+            };
+            self.hashtag_hour_posts.remove(&(hour, post_id));
+            if let Some(mut hashtag_hours) = self.post_hashtag_hours.get(&post_id) {
+                hashtag_hours.retain(|(_, tag_hour)| *tag_hour != hour);
+                store_unless_empty(
+                    &mut self.post_hashtag_hours,
+                    post_id,
+                    &hashtag_hours,
+                    hashtag_hours.is_empty(),
+                );
+            }
+            budget -= 1;
+        }
+    }
+
+    // Take back a post's use of each hashtag from the hour it was counted in
+    fn remove_hashtag_usage(&mut self, post_id: u64, hashtags: &[String]) {
+        let mut hashtag_hours = match self.post_hashtag_hours.get(&post_id) {
+            None => return,
+            Some(hashtag_hours) => hashtag_hours,
+        };
+        for (tag, hour) in hashtag_hours
+            .iter()
+            .filter(|(tag, _)| hashtags.contains(tag))
+        {
+            // The hour may already have been dropped from the trending window
+            if let Some(mut usage) = self.hashtag_usage.get(hour) {
+                match usage.get(tag).unwrap_or(0) {
+                    0 => continue,
+                    1 => usage.remove(tag),
+                    count => usage.insert(tag, &(count - 1)),
+                };
+                if usage.is_empty() {
+                    self.hashtag_usage.remove(hour);
+                } else {
+                    self.hashtag_usage.insert(hour, &usage);
+                }
+            }
+        }
+
+        // Uses from hours past the trending window have nothing left to take back either
+        let oldest_hour = (env::block_timestamp() / NANOSECONDS_PER_HOUR)
+            .saturating_sub(MAX_TRENDING_WINDOW_HOURS);
+        let previous_hours: Vec<u64> = hashtag_hours.iter().map(|(_, hour)| *hour).collect();
+        hashtag_hours.retain(|(tag, hour)| !hashtags.contains(tag) && *hour >= oldest_hour);
+        for hour in previous_hours {
+            if !hashtag_hours
+                .iter()
+                .any(|(_, kept_hour)| *kept_hour == hour)
+            {
+                self.hashtag_hour_posts.remove(&(hour, post_id));
+            }
+        }
+        store_unless_empty(
+            &mut self.post_hashtag_hours,
+            post_id,
+            &hashtag_hours,
+            hashtag_hours.is_empty(),
         );
     }
+
+    // Add a mention to the feed of every registered account `@mentioned` in `content`, accounts
+    // already in `previous` keep their mention, returns the (mentioned account, mention_id) pairs
+    // to keep on the post or comment
+    fn insert_mentions(
+        &mut self,
+        content: &str,
+        author: &AccountId,
+        post_id: u64,
+        comment_id: Option<u64>,
+        previous: &[(AccountId, u64)],
+    ) -> Vec<(AccountId, u64)> {
+        let mut mentions = Vec::new();
+        for account_id in extract_mentions(content) {
+            if mentions.len() == MAX_MENTIONS_PER_RECORD {
+                break;
+            }
+            if let Some(mention) = previous.iter().find(|(kept, _)| *kept == account_id) {
+                mentions.push(mention.clone());
+                continue;
+            }
+            if !self.user_list.contains_key(&account_id) {
+                continue;
+            }
+            self.mention_counter += 1;
+            let mut account_mentions = self.mentions_of(&account_id);
+            account_mentions.insert(
+                &self.mention_counter,
+                &post::Mention {
+                    mention_id: self.mention_counter,
+                    post_id,
+                    comment_id,
+                    user_address: author.clone(),
+                    created_at: env::block_timestamp(),
+                },
+            );
+            self.mentions.insert(&account_id, &account_mentions);
+            mentions.push((account_id, self.mention_counter));
+        }
+        mentions
+    }
+
+    // Re-index the mentions of edited content, returns the pairs to keep on the post or comment
+    fn update_mentions(
+        &mut self,
+        previous: Vec<(AccountId, u64)>,
+        content: &str,
+        author: &AccountId,
+        post_id: u64,
+        comment_id: Option<u64>,
+    ) -> Vec<(AccountId, u64)> {
+        let mentions = self.insert_mentions(content, author, post_id, comment_id, &previous);
+        let dropped: Vec<(AccountId, u64)> = previous
+            .into_iter()
+            .filter(|mention| !mentions.contains(mention))
+            .collect();
+        self.remove_mentions(&dropped);
+        mentions
+    }
+
+    fn remove_mentions(&mut self, mentions: &[(AccountId, u64)]) {
+        for (account_id, mention_id) in mentions {
+            let mut account_mentions = self.mentions_of(account_id);
+            account_mentions.remove(mention_id);
+            self.mentions.insert(account_id, &account_mentions);
+        }
+    }
+
+    fn mentioned_accounts(&self, mentions: Option<Vec<(AccountId, u64)>>) -> Vec<AccountId> {
+        mentions
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(account_id, _)| account_id)
+            .collect()
+    }
+
+    fn mentions_of(&self, account_id: &AccountId) -> TreeMap<u64, post::Mention> {
+        self.mentions.get(account_id).unwrap_or_else(|| {
+            TreeMap::new(StorageKeys::MentionsOf {
+                account_hash: env::sha256(account_id.as_bytes()),
+            })
+        })
+    }
+
+    fn post_quotes_of(&self, post_id: u64) -> TreeMap<u64, ()> {
+        self.post_quotes
+            .get(&post_id)
+            .unwrap_or_else(|| TreeMap::new(StorageKeys::PostQuotesOf { post_id }))
+    }
+
+    fn post_revisions_of(&self, post_id: u64) -> Vector<post::PostRevision> {
+        self.post_revisions
+            .get(&post_id)
+            .unwrap_or_else(|| Vector::new(StorageKeys::PostRevisionsOf { post_id }))
+    }
+
+    fn user_posts_of(&self, account_id: &AccountId) -> TreeMap<u64, ()> {
+        self.user_posts.get(account_id).unwrap_or_else(|| {
+            TreeMap::new(StorageKeys::UserPostsOf {
+                account_hash: env::sha256(account_id.as_bytes()),
+            })
+        })
+    }
+
+    fn assert_post_exists(&self, post_id: u64) {
+        require!(self.posts.contains_key(&post_id), "Post does not exist!");
+    }
+
+    // Remove up to `limit` of the likes, reactions, reposts, comments, poll votes, quotes and
+    // revisions left behind by a deleted post, returns how many were removed
+    fn clear_post_records(&mut self, post_id: u64, limit: u64) -> u64 {
+        let mut removed = 0;
+
+        let mut likers = self.post_likers_of(post_id);
+        while removed < limit {
+            let like = match likers.min().and_then(|like_id| likers.remove(&like_id)) {
+                None => break,
+                Some(like) => like,
+            };
+            self.post_like_ids.remove(&(post_id, like.user_address));
+            removed += 1;
+        }
+        store_unless_empty(&mut self.post_likers, post_id, &likers, likers.is_empty());
+
+        self.reaction_counts.remove(&post_id);
+        let mut reactions = self.post_reactions_of(post_id);
+        let reactors: Vec<AccountId> = reactions.keys().take((limit - removed) as usize).collect();
+        for reactor in reactors {
+            reactions.remove(&reactor);
+            removed += 1;
+        }
+        store_unless_empty(
+            &mut self.post_reactions,
+            post_id,
+            &reactions,
+            reactions.is_empty(),
+        );
+
+        while removed < limit {
+            match self.post_reposts_of(post_id).min() {
+                None => break,
+                Some(repost_id) => self.remove_repost(repost_id),
+            }
+            removed += 1;
+        }
+        if self.post_reposts_of(post_id).is_empty() {
+            self.post_reposts.remove(&post_id);
+        }
+
+        let mut ranking = self.post_top_comments_of(post_id);
+        while removed < limit {
+            match ranking.min() {
+                None => break,
+                Some(key) => ranking.remove(&key),
+            };
+            removed += 1;
+        }
+        store_unless_empty(
+            &mut self.post_top_comments,
+            post_id,
+            &ranking,
+            ranking.is_empty(),
+        );
+
+        // Newest first, so a comment's replies are gone by the time it is removed
+        let mut comment_ids = self.post_comment_ids_of(post_id);
+        while removed < limit {
+            let comment_id = match comment_ids.max() {
+                None => break,
+                Some(comment_id) => comment_id,
+            };
+            let mut comment_likers = self.comment_likers_of(comment_id);
+            let accounts: Vec<AccountId> = comment_likers
+                .iter()
+                .take((limit - removed) as usize)
+                .collect();
+            for account in accounts {
+                comment_likers.remove(&account);
+                removed += 1;
+            }
+            store_unless_empty(
+                &mut self.comment_likers,
+                comment_id,
+                &comment_likers,
+                comment_likers.is_empty(),
+            );
+            if removed == limit || !comment_likers.is_empty() {
+                break;
+            }
+
+            if let Some(parent_comment_id) = self.comment_parents.get(&comment_id) {
+                let mut replies = self.comment_replies_of(parent_comment_id);
+                replies.remove(&comment_id);
+                store_unless_empty(
+                    &mut self.comment_replies,
+                    parent_comment_id,
+                    &replies,
+                    replies.is_empty(),
+                );
+            }
+            comment_ids.remove(&comment_id);
+            self.remove_comment_data(comment_id);
+            removed += 1;
+        }
+        store_unless_empty(
+            &mut self.post_comment_ids,
+            post_id,
+            &comment_ids,
+            comment_ids.is_empty(),
+        );
+
+        let mut votes = self
+            .poll_votes
+            .get(&post_id)
+            .unwrap_or_else(|| UnorderedMap::new(StorageKeys::PollVotesOf { post_id }));
+        let voters: Vec<AccountId> = votes.keys().take((limit - removed) as usize).collect();
+        for voter in voters {
+            votes.remove(&voter);
+            removed += 1;
+        }
+        store_unless_empty(&mut self.poll_votes, post_id, &votes, votes.is_empty());
+
+        // Quotes of a deleted post stay up and show it as unavailable
+        let mut quotes = self.post_quotes_of(post_id);
+        while removed < limit {
+            match quotes.min() {
+                None => break,
+                Some(quoting_post_id) => quotes.remove(&quoting_post_id),
+            };
+            removed += 1;
+        }
+        store_unless_empty(&mut self.post_quotes, post_id, &quotes, quotes.is_empty());
+
+        let mut revisions = self.post_revisions_of(post_id);
+        while removed < limit && revisions.pop().is_some() {
+            removed += 1;
+        }
+        store_unless_empty(
+            &mut self.post_revisions,
+            post_id,
+            &revisions,
+            revisions.is_empty(),
+        );
+
+        removed
+    }
+
+    fn expect_post(&self, post_id: u64) -> post::PostDetail {
+        let post = self.posts.get(&post_id);
+        require!(post.is_some(), "Post does not exist!");
+        post.unwrap()
+    }
+
+    // Store a comment and add it to its post's comment index
+    fn insert_comment(&mut self, comment: &post::PostComment) {
+        self.comments.insert(&comment.comment_id, comment);
+
+        let mut comment_ids = self.post_comment_ids_of(comment.post_id);
+        comment_ids.insert(&comment.comment_id, &());
+        self.post_comment_ids.insert(&comment.post_id, &comment_ids);
+
+        let mut ranking = self.post_top_comments_of(comment.post_id);
+        ranking.insert(&(0, comment.comment_id), &());
+        self.post_top_comments.insert(&comment.post_id, &ranking);
+    }
+
+    // Remove a comment record and its post's index entries, then queue its replies and likes
+    // for `clear_deleted_comments`
+    fn detach_comment(&mut self, comment_id: u64) {
+        let post_comment = match self.comments.remove(&comment_id) {
+            None => return,
+            Some(post_comment) => post_comment,
+        };
+        let post_id = post_comment.post_id;
+
+        let mut comment_ids = self.post_comment_ids_of(post_id);
+        comment_ids.remove(&comment_id);
+        store_unless_empty(
+            &mut self.post_comment_ids,
+            post_id,
+            &comment_ids,
+            comment_ids.is_empty(),
+        );
+        let mut ranking = self.post_top_comments_of(post_id);
+        ranking.remove(&(self.comment_like_count(comment_id), comment_id));
+        store_unless_empty(
+            &mut self.post_top_comments,
+            post_id,
+            &ranking,
+            ranking.is_empty(),
+        );
+
+        self.comment_edited_at.remove(&comment_id);
+        self.comment_parents.remove(&comment_id);
+        if let Some(mentions) = self.comment_mentions.remove(&comment_id) {
+            self.remove_mentions(&mentions);
+        }
+        self.comment_attachments.remove(&comment_id);
+        self.deleted_comment_ids.push(&comment_id);
+    }
+
+    // Remove a comment record and everything attached to it, its post's comment index is left to the caller
+    fn remove_comment_data(&mut self, comment_id: u64) {
+        self.comments.remove(&comment_id);
+        self.comment_edited_at.remove(&comment_id);
+        self.comment_parents.remove(&comment_id);
+        if let Some(mut replies) = self.comment_replies.remove(&comment_id) {
+            replies.clear();
+        }
+        if let Some(mut likers) = self.comment_likers.remove(&comment_id) {
+            likers.clear();
+        }
+        if let Some(mentions) = self.comment_mentions.remove(&comment_id) {
+            self.remove_mentions(&mentions);
+        }
+        self.comment_attachments.remove(&comment_id);
+    }
+
+    // A comment with the first page of its replies, `depth` more levels deep and at most `budget`
+    // replies in total, a level's replies are taken from the budget before any of their own replies
+    fn comment_thread(
+        &self,
+        comment: post::PostComment,
+        depth: u64,
+        budget: &mut u64,
+        viewer: Option<&AccountId>,
+    ) -> post::CommentThread {
+        let mut replies = vec![];
+        let mut replies_cursor = None;
+        if depth > 0 && *budget > 0 {
+            let (reply_ids, next_cursor) = paginate(
+                &self.comment_replies_of(comment.comment_id),
+                None,
+                Some(DEFAULT_PAGE_LIMIT.min(*budget)),
+                false,
+            );
+            replies_cursor = next_cursor;
+            let page: Vec<post::PostComment> = reply_ids
+                .into_iter()
+                .filter_map(|(reply_id, _)| self.comments.get(&reply_id))
+                .collect();
+            *budget -= page.len() as u64;
+            replies = page
+                .into_iter()
+                .map(|reply| self.comment_thread(reply, depth - 1, budget, viewer))
+                .collect();
+        }
+        post::CommentThread {
+            comment: self.comment_output(comment, viewer),
+            replies,
+            replies_cursor,
+        }
+    }
+
+    fn comment_replies_of(&self, comment_id: u64) -> TreeMap<u64, ()> {
+        self.comment_replies
+            .get(&comment_id)
+            .unwrap_or_else(|| TreeMap::new(StorageKeys::CommentRepliesOf { comment_id }))
+    }
+
+    fn expect_comment(&self, comment_id: u64) -> post::PostComment {
+        let comment = self.comments.get(&comment_id);
+        require!(comment.is_some(), "Comment does not exist!");
+        comment.unwrap()
+    }
+
+    // Comment output, `is_liked` is only filled in when there is a viewer
+    fn comment_output(
+        &self,
+        comment: post::PostComment,
+        viewer: Option<&AccountId>,
+    ) -> post::PostCommentDetailsOutput {
+        let likers = self.comment_likers.get(&comment.comment_id);
+        post::PostCommentDetailsOutput {
+            comment_id: comment.comment_id,
+            profile_image_url: self.profile_of(&comment.user_address).profile_image_url,
+            user_address: comment.user_address,
+            comment: comment.comment,
+            created_at: comment.created_at,
+            edited_at: self.comment_edited_at.get(&comment.comment_id),
+            parent_comment_id: self.comment_parents.get(&comment.comment_id),
+            reply_count: self
+                .comment_replies
+                .get(&comment.comment_id)
+                .map_or(0, |replies| replies.len()),
+            like_count: likers.as_ref().map_or(0, |likers| likers.len()),
+            is_liked: viewer.map(|account| {
+                likers
+                    .as_ref()
+                    .is_some_and(|likers| likers.contains(account))
+            }),
+            mentions: self.mentioned_accounts(self.comment_mentions.get(&comment.comment_id)),
+            attachments: self
+                .comment_attachments
+                .get(&comment.comment_id)
+                .unwrap_or_default(),
+        }
+    }
+
+    // A page of a post's comments in `sort` order, oldest first by default
+    fn comments_page(
+        &self,
+        post_id: u64,
+        cursor: Option<u64>,
+        limit: Option<u64>,
+        sort: Option<post::CommentSortOrder>,
+    ) -> (Vec<post::PostComment>, Option<u64>) {
+        let (comment_ids, next_cursor) = match sort {
+            Some(post::CommentSortOrder::TopLiked) => {
+                self.top_comment_ids(post_id, cursor.unwrap_or(0), limit)
+            }
+            _ => {
+                let (comment_ids, next_cursor) = paginate(
+                    &self.post_comment_ids_of(post_id),
+                    cursor,
+                    limit,
+                    sort == Some(post::CommentSortOrder::NewestFirst),
+                );
+                let comment_ids = comment_ids.into_iter().map(|(id, _)| id).collect();
+                (comment_ids, next_cursor)
+            }
+        };
+        let comments = comment_ids
+            .into_iter()
+            .filter_map(|comment_id| self.comments.get(&comment_id))
+            .collect();
+        (comments, next_cursor)
+    }
+
+    // A page of a post's comment_ids with the most liked first, the cursor is an offset
+    fn top_comment_ids(
+        &self,
+        post_id: u64,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> (Vec<u64>, Option<u64>) {
+        let limit = page_limit(limit);
+        let mut comment_ids: Vec<u64> = self
+            .post_top_comments_of(post_id)
+            .iter_rev()
+            .skip(offset as usize)
+            .take(limit as usize + 1)
+            .map(|((_, comment_id), _)| comment_id)
+            .collect();
+        let mut next_cursor = None;
+        if comment_ids.len() > limit as usize {
+            comment_ids.truncate(limit as usize);
+            next_cursor = Some(offset + limit);
+        }
+        (comment_ids, next_cursor)
+    }
+
+    fn comment_like_count(&self, comment_id: u64) -> u64 {
+        self.comment_likers
+            .get(&comment_id)
+            .map_or(0, |likers| likers.len())
+    }
+
+    fn comment_likers_of(&self, comment_id: u64) -> UnorderedSet<AccountId> {
+        self.comment_likers
+            .get(&comment_id)
+            .unwrap_or_else(|| UnorderedSet::new(StorageKeys::CommentLikersOf { comment_id }))
+    }
+
+    fn post_top_comments_of(&self, post_id: u64) -> TreeMap<(u64, u64), ()> {
+        self.post_top_comments
+            .get(&post_id)
+            .unwrap_or_else(|| TreeMap::new(StorageKeys::PostTopCommentsOf { post_id }))
+    }
+
+    fn post_comment_count(&self, post_id: u64) -> u64 {
+        self.post_comment_ids
+            .get(&post_id)
+            .map_or(0, |comment_ids| comment_ids.len())
+    }
+
+    fn post_comment_ids_of(&self, post_id: u64) -> TreeMap<u64, ()> {
+        self.post_comment_ids
+            .get(&post_id)
+            .unwrap_or_else(|| TreeMap::new(StorageKeys::PostCommentIdsOf { post_id }))
+    }
+
+    fn following_of(&self, account_id: &AccountId) -> TreeMap<u64, AccountId> {
+        self.following.get(account_id).unwrap_or_else(|| {
+            TreeMap::new(StorageKeys::FollowingOf {
+                account_hash: env::sha256(account_id.as_bytes()),
+            })
+        })
+    }
+
+    fn followers_of(&self, account_id: &AccountId) -> TreeMap<u64, AccountId> {
+        self.followers.get(account_id).unwrap_or_else(|| {
+            TreeMap::new(StorageKeys::FollowersOf {
+                account_hash: env::sha256(account_id.as_bytes()),
+            })
+        })
+    }
+}
+
+// Write a nested collection back to its map, or drop the map entry once the collection is empty
+fn store_unless_empty<T: BorshSerialize + BorshDeserialize>(
+    map: &mut LookupMap<u64, T>,
+    key: u64,
+    collection: &T,
+    is_empty: bool,
+) {
+    if is_empty {
+        map.remove(&key);
+    } else {
+        map.insert(&key, collection);
+    }
+}
+
+fn default_reaction_kinds() -> UnorderedSet<String> {
+    let mut reaction_kinds = UnorderedSet::new(StorageKeys::ReactionKinds);
+    reaction_kinds.insert(&DEFAULT_REACTION.to_string());
+    reaction_kinds
+}
+
+// Lowercased `#tags` in `content` in order of appearance, without duplicates
+fn extract_hashtags(content: &str) -> Vec<String> {
+    let hashtags = extract_tokens(content, '#', |c| c.is_alphanumeric() || c == '_')
+        .into_iter()
+        .filter(|tag| tag.len() <= MAX_HASHTAG_LENGTH)
+        .map(|tag| tag.to_lowercase())
+        .collect::<Vec<String>>();
+    let mut unique: Vec<String> = Vec::new();
+    for tag in hashtags {
+        if !unique.contains(&tag) {
+            unique.push(tag);
+        }
+    }
+    unique.truncate(MAX_HASHTAGS_PER_POST);
+    unique
+}
+
+fn assert_valid_poll(poll: &post::NewPoll) {
+    require!(
+        poll.options.len() >= MIN_POLL_OPTIONS && poll.options.len() <= MAX_POLL_OPTIONS,
+        "A poll must have between 2 and 10 options!"
+    );
+    require!(
+        poll.options
+            .iter()
+            .all(|option| !option.is_empty() && option.len() <= MAX_POLL_OPTION_LENGTH),
+        "Poll options must be between 1 and 100 bytes!"
+    );
+    require!(
+        poll.closes_at > env::block_timestamp(),
+        "Poll must close in the future!"
+    );
+}
+
+fn assert_valid_attachments(attachments: &[post::Attachment]) {
+    require!(
+        attachments.len() <= MAX_ATTACHMENTS,
+        "At most 4 attachments are allowed!"
+    );
+    let mut metadata_bytes = 0;
+    for attachment in attachments {
+        require!(
+            !attachment.uri.is_empty(),
+            "Attachment URI cannot be empty!"
+        );
+        require!(
+            attachment.mime_type.contains('/'),
+            "Attachment MIME type is invalid!"
+        );
+        metadata_bytes += attachment.uri.len()
+            + attachment.mime_type.len()
+            + attachment.alt_text.as_ref().map_or(0, String::len)
+            + attachment.content_hash.as_ref().map_or(0, String::len);
+    }
+    require!(
+        metadata_bytes <= MAX_ATTACHMENT_METADATA_BYTES,
+        "Attachment metadata cannot exceed 2048 bytes!"
+    );
+}
+
+// Valid account IDs `@mentioned` in `content` in order of appearance, without duplicates
+fn extract_mentions(content: &str) -> Vec<AccountId> {
+    let mut mentions: Vec<AccountId> = Vec::new();
+    let tokens = extract_tokens(content, '@', |c| {
+        c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '.' | '-' | '_')
+    });
+    for token in tokens {
+        // Punctuation ending a sentence is not part of the account ID
+        let token = token.trim_end_matches(['.', '-', '_']);
+        if let Ok(account_id) = token.parse::<AccountId>() {
+            if !mentions.contains(&account_id) {
+                mentions.push(account_id);
+            }
+        }
+    }
+    mentions
+}
+
+// Runs of `is_token_char` characters following `marker` at the start of a word in `content`
+fn extract_tokens(content: &str, marker: char, is_token_char: fn(char) -> bool) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut previous = None;
+    let mut chars = content.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let at_word_start = previous.is_none_or(|p: char| !p.is_alphanumeric() && p != '_');
+        previous = Some(c);
+        if c != marker || !at_word_start {
+            continue;
+        }
+        let start = i + c.len_utf8();
+        let mut end = start;
+        while let Some(&(j, next)) = chars.peek() {
+            if !is_token_char(next) {
+                break;
+            }
+            end = j + next.len_utf8();
+            previous = Some(next);
+            chars.next();
+        }
+        if end > start {
+            tokens.push(content[start..end].to_string());
+        }
+    }
+    tokens
+}
+
+fn page_limit(limit: Option<u64>) -> u64 {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+    require!(
+        limit > 0 && limit <= MAX_PAGE_LIMIT,
+        "Limit must be between 1 and 100!"
+    );
+    limit
+}
+
+// Take up to `limit` entries of `tree` past the `cursor` key, along with the cursor of the next page
+fn paginate<V>(
+    tree: &TreeMap<u64, V>,
+    cursor: Option<u64>,
+    limit: Option<u64>,
+    newest_first: bool,
+) -> (Vec<(u64, V)>, Option<u64>)
+where
+    V: BorshSerialize + BorshDeserialize,
+{
+    let limit = page_limit(limit);
+    let entries: Box<dyn Iterator<Item = (u64, V)> + '_> = match (cursor, newest_first) {
+        (None, false) => Box::new(tree.iter()),
+        (None, true) => Box::new(tree.iter_rev()),
+        (Some(key), false) => Box::new(tree.iter_from(key)),
+        (Some(key), true) => Box::new(tree.iter_rev_from(key)),
+    };
+    let mut page: Vec<(u64, V)> = entries.take(limit as usize + 1).collect();
+    let mut next_cursor = None;
+    if page.len() > limit as usize {
+        page.truncate(limit as usize);
+        next_cursor = page.last().map(|(key, _)| *key);
+    }
+    (page, next_cursor)
+}
+
+/*
+ * the rest of this file sets up unit tests
+ * to run these, the command will be:
+ * cargo test --package rust-template -- --nocapture
+ * Note: 'rust-template' comes from Cargo.toml's 'name' key
+ */
+
+// use the attribute below for unit tests
+#[cfg(test)]
+mod tests {
+    use near_sdk::{testing_env, VMContext};
+
+    use super::*;
+
+    // part of writing unit tests is setting up a mock context
+    // in this example, this is only needed for env::log in the contract
+    // this is also a useful list to peek at when wondering what's available in env::*
+    fn get_context(input: Vec<u8>) -> VMContext {
+        VMContext {
+            current_account_id: "alice.testnet".to_string().parse().unwrap(),
+            signer_account_id: "robert.testnet".to_string().parse().unwrap(),
+            signer_account_pk: vec![0, 1, 2],
+            predecessor_account_id: "jane.testnet".to_string().parse().unwrap(),
+            input,
+            block_index: 0,
+            block_timestamp: 0,
+            account_balance: 0,
+            account_locked_balance: 0,
+            storage_usage: 0,
+            attached_deposit: 0,
+            prepaid_gas: 10u64.pow(18),
+            random_seed: vec![0, 1, 2],
+            output_data_receivers: vec![],
+            epoch_height: 19,
+            view_config: None,
+        }
+    }
+
+    fn set_signer(signer: &str) {
+        let mut ctx = get_context(vec![]);
+        ctx.signer_account_id = signer.parse().unwrap();
+        ctx.storage_usage = env::storage_usage();
+        testing_env!(ctx);
+    }
+
+    fn register(contract: &mut Contract, signer: &str) -> AccountId {
+        set_signer(signer);
+        contract.create_account(Some(signer.into()), None, None, None, None);
+        signer.parse().unwrap()
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_create_account() {
+        let ctx = get_context(vec![]);
+        testing_env!(ctx);
+
+        let mut contract = Contract::new();
+        println!(
+            "[ACCOUNT NOT CREATED]: check_user_exist value is [{}]",
+            contract.is_user_exists("robert.testnet".to_string().parse().unwrap())
+        );
+
+        contract.create_account(
+            Some("Robert".into()),
+            Some("".into()),
+            Some("".into()),
+            Some("".into()),
+            Some("".into()),
+        );
+        println!(
+            "[ACCOUNT CREATED]: check_user_exist value is [{}]",
+            contract.is_user_exists("robert.testnet".to_string().parse().unwrap())
+        );
+
+        contract.create_account(
+            Some("Robert2".into()),
+            Some("".into()),
+            Some("".into()),
+            Some("".into()),
+            Some("".into()),
+        );
+    }
+
+    #[test]
+    fn test_change_profile_image() {
+        let ctx = get_context(vec![]);
+        testing_env!(ctx);
+
+        let mut contract = Contract::new();
+        contract.create_account(
+            Some("Mond".into()),
+            Some("changethis".into()),
+            Some("".into()),
+            Some("".into()),
+            Some("".into()),
+        );
+
+        println!(
+            "prev image url: {:?}",
+            contract
+                .get_account_details("robert.testnet".to_string().parse().unwrap())
+                .unwrap()
+                .profile_image_url
+        );
+
+        contract.edit_profile_image("changed!".into());
+        println!(
+            "after image url: {:?}",
+            contract.get_account_details("robert.testnet".to_string().parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_follow_and_unfollow() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        let jane = register(&mut contract, "jane.testnet");
+
+        set_signer("robert.testnet");
+        contract.follow_user(jane.clone());
+        assert!(contract.is_user_followed(&robert, &jane));
+        assert!(!contract.is_user_followed(&jane, &robert));
+        assert_eq!(contract.get_user_following_count(robert.clone()), 1);
+        assert_eq!(contract.get_user_followers_count(jane.clone()), 1);
+
+        let followers = contract
+            .get_user_followers_list(jane.clone(), None, None)
+            .items;
+        assert_eq!(followers.len(), 1);
+        assert_eq!(followers[0].user_account_id, robert);
+        assert!(!followers[0].is_followed);
+
+        contract.follow_user(jane.clone());
+        assert!(!contract.is_user_followed(&robert, &jane));
+        assert!(contract
+            .get_user_following_list(robert, None, None)
+            .items
+            .is_empty());
+        assert_eq!(contract.get_user_followers_count(jane), 0);
+    }
+
+    #[test]
+    fn test_migrate_follow_graph() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        let jane = register(&mut contract, "jane.testnet");
+        contract.legacy_user_followers.extend(vec![
+            user::UserFollowers {
+                user_account_id: robert.clone(),
+                follower_account_id: jane.clone(),
+            },
+            user::UserFollowers {
+                user_account_id: jane.clone(),
+                follower_account_id: robert.clone(),
+            },
+        ]);
+        contract.follow_counter = 2;
+
+        assert_eq!(contract.migrate_follow_graph(1), 1);
+        assert_eq!(contract.migrate_follow_graph(10), 0);
+        assert!(contract.is_user_followed(&robert, &jane));
+        assert!(contract.is_user_followed(&jane, &robert));
+        assert_eq!(contract.follow_edges.get(&(robert.clone(), jane)), Some(1));
+
+        let alice = register(&mut contract, "alice.testnet");
+        set_signer("robert.testnet");
+        contract.follow(alice);
+        assert_eq!(contract.get_user_following_count(robert), 2);
+        assert_eq!(contract.follow_counter, 3);
+    }
+
+    #[test]
+    fn test_like_and_unlike_post() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        let jane = register(&mut contract, "jane.testnet");
+        contract.create_post("hello".into(), None, None, None);
+
+        contract.like_post(1);
+        set_signer("robert.testnet");
+        contract.like_post(1);
+        let post = contract.get_single_post(1, Some(robert.clone()), None);
+        assert_eq!(post.like_count, 2);
+        assert_eq!(post.is_liked, Some(true));
+        assert_eq!(
+            contract.get_post_likes_details(1, None, None, None).items[0].user_address,
+            jane
+        );
+
+        contract.like_post(1);
+        let post = contract.get_single_post(1, Some(robert), None);
+        assert_eq!(post.like_count, 1);
+        assert_eq!(post.is_liked, Some(false));
+    }
+
+    #[test]
+    fn test_migrate_post_likes() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        contract.legacy_post_likes.push(&post::PostLikes {
+            post_id: 1,
+            user_address: robert.clone(),
+            created_at: 0,
+        });
+        contract.like_counter = 1;
+
+        assert_eq!(contract.migrate_post_likes(10), 0);
+        assert_eq!(
+            contract
+                .get_all_posts(Some(robert.clone()), None, None)
+                .items[0]
+                .like_count,
+            1
+        );
+
+        contract.like_post(1);
+        assert_eq!(
+            contract.get_all_posts(Some(robert), None, None).items[0].is_liked,
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn test_comment_on_post() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        contract.create_post("first".into(), None, None, None);
+        contract.create_post("second".into(), None, None, None);
+        contract.legacy_post_comments.push(&post::PostComment {
+            comment_id: 1,
+            post_id: 1,
+            user_address: robert.clone(),
+            comment: "legacy".into(),
+            created_at: 0,
+        });
+        contract.comment_counter = 1;
+
+        contract.comment_on_post(1, "new".into(), None, None);
+        contract.comment_on_post(2, "other".into(), None, None);
+        assert_eq!(contract.migrate_post_comments(10), 0);
+
+        let comments = contract
+            .get_post_comment_details(1, None, None, None, None)
+            .items;
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].comment, "legacy");
+        assert_eq!(comments[1].comment_id, 2);
+        assert_eq!(contract.get_single_post(1, None, None).comment_count, 2);
+        assert_eq!(
+            contract.get_user_posts(robert, None, None, None).items[0].comment_count,
+            1
+        );
+    }
+
+    #[test]
+    fn test_post_lookup() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        contract.legacy_all_posts.push(&post::PostDetail {
+            post_id: 1,
+            user_address: robert.clone(),
+            content: "legacy".into(),
+            created_at: 0,
+        });
+        contract.post_counter = 1;
+        contract.create_post("new".into(), None, None, None);
+        assert_eq!(contract.migrate_posts(10), 0);
+
+        assert_eq!(contract.get_poster_address(1), robert);
+        assert_eq!(contract.get_post(2).unwrap().content, "new");
+        assert!(contract.get_post(3).is_none());
+        let feed = contract.get_all_posts(None, None, None).items;
+        assert_eq!(feed[0].post.post_id, 2);
+        assert_eq!(feed[1].post.post_id, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Post does not exist!")]
+    fn test_get_missing_post() {
+        let contract = Contract::new();
+        contract.get_single_post(1, None, None);
+    }
+
+    #[test]
+    fn test_get_user_posts() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        contract.create_post("first".into(), None, None, None);
+        register(&mut contract, "jane.testnet");
+        contract.create_post("jane's".into(), None, None, None);
+        set_signer("robert.testnet");
+        contract.create_post("second".into(), None, None, None);
+
+        let posts = contract.get_user_posts(robert, None, None, None).items;
+        assert_eq!(posts.len(), 2);
+        assert_eq!(posts[0].post.content, "second");
+        assert_eq!(posts[1].post.content, "first");
+    }
+
+    #[test]
+    fn test_paginate_posts() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        for i in 1..=5 {
+            contract.create_post(format!("post {}", i), None, None, None);
+        }
+
+        let page = contract.get_all_posts(None, None, Some(2));
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.items[0].post.post_id, 5);
+        assert_eq!(page.next_cursor, Some(4));
+
+        let page = contract.get_user_posts(robert.clone(), None, page.next_cursor, Some(2));
+        assert_eq!(page.items[0].post.post_id, 3);
+        assert_eq!(page.next_cursor, Some(2));
+
+        let page = contract.get_user_posts(robert, None, page.next_cursor, Some(2));
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn test_paginate_follow_lists() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        let accounts = ["a.testnet", "b.testnet", "c.testnet"];
+        for account in accounts {
+            register(&mut contract, account);
+            contract.follow(robert.clone());
+        }
+        set_signer("robert.testnet");
+        contract.follow("b.testnet".parse().unwrap());
+
+        let page = contract.get_user_followers_list(robert.clone(), None, Some(2));
+        assert_eq!(page.items[0].user_account_id.as_str(), "a.testnet");
+        assert!(page.items[1].is_followed);
+        assert_eq!(page.next_cursor, Some(2));
+
+        let page = contract.get_user_followers_list(robert.clone(), page.next_cursor, Some(2));
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].user_account_id.as_str(), "c.testnet");
+        assert_eq!(page.next_cursor, None);
+
+        let page = contract.get_user_following_list(robert, None, None);
+        assert_eq!(page.items.len(), 1);
+    }
+
+    #[test]
+    fn test_paginate_comments() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        for i in 1..=3 {
+            contract.comment_on_post(1, format!("comment {}", i), None, None);
+        }
+
+        let page = contract.get_post_comment_details(
+            1,
+            None,
+            Some(2),
+            Some(post::CommentSortOrder::NewestFirst),
+            None,
+        );
+        assert_eq!(page.items[0].comment_id, 3);
+        assert_eq!(page.items[1].comment_id, 2);
+        let page = contract.get_post_comment_details(
+            1,
+            page.next_cursor,
+            Some(2),
+            Some(post::CommentSortOrder::NewestFirst),
+            None,
+        );
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].comment_id, 1);
+
+        let post = contract.get_single_post(1, None, Some(1));
+        assert_eq!(post.comment_count, 3);
+        assert_eq!(post.comment_details.unwrap()[0].comment_id, 1);
+    }
+
+    #[test]
+    fn test_follow_counts() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        let jane = register(&mut contract, "jane.testnet");
+        contract.follow(robert.clone());
+
+        let details = contract.get_account_details(robert.clone()).unwrap();
+        assert_eq!(details.followers_count, 1);
+        assert_eq!(details.following_count, 0);
+        assert_eq!(
+            contract
+                .get_account_details(jane.clone())
+                .unwrap()
+                .following_count,
+            1
+        );
+
+        contract.unfollow(robert.clone());
+        assert_eq!(
+            contract
+                .get_account_details(robert.clone())
+                .unwrap()
+                .followers_count,
+            0
+        );
+
+        contract.follow(robert.clone());
+        contract.update_account(&robert, |account| account.followers_count = 0);
+        contract.recompute_follow_counts(vec![robert.clone(), jane]);
+        assert_eq!(
+            contract
+                .get_account_details(robert)
+                .unwrap()
+                .followers_count,
+            1
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "You cannot follow yourself!")]
+    fn test_follow_self() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        contract.follow(robert);
+    }
+
+    #[test]
+    #[should_panic(expected = "User does not exist!")]
+    fn test_follow_unregistered_account() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.follow("ghost.testnet".parse().unwrap());
+    }
+
+    #[test]
+    fn test_follow_list_with_dangling_edge() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        let ghost: AccountId = "ghost.testnet".parse().unwrap();
+        contract.insert_follow_edge(1, &robert, &ghost);
+
+        let following = contract.get_user_following_list(robert, None, None).items;
+        assert_eq!(following[0].user_account_id, ghost);
+        assert_eq!(following[0].profile_image_url, "");
+    }
+
+    #[test]
+    fn test_follow_is_idempotent() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        register(&mut contract, "jane.testnet");
+
+        assert!(contract.follow(robert.clone()));
+        assert!(!contract.follow(robert.clone()));
+        assert_eq!(contract.get_user_followers_count(robert.clone()), 1);
+
+        assert!(contract.unfollow(robert.clone()));
+        assert!(!contract.unfollow(robert.clone()));
+        assert_eq!(contract.get_user_followers_count(robert), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Account does not exist!")]
+    fn test_create_post_unregistered() {
+        let mut contract = Contract::new();
+        contract.create_post("hello".into(), None, None, None);
+    }
+
+    #[test]
+    fn test_feed_with_orphaned_records() {
+        let mut contract = Contract::new();
+        let ghost: AccountId = "ghost.testnet".parse().unwrap();
+        contract.insert_post(&post::PostDetail {
+            post_id: 1,
+            user_address: ghost.clone(),
+            content: "legacy".into(),
+            created_at: 0,
+        });
+        contract.insert_comment(&post::PostComment {
+            comment_id: 1,
+            post_id: 1,
+            user_address: ghost.clone(),
+            comment: "legacy".into(),
+            created_at: 0,
+        });
+
+        let feed = contract.get_all_posts(None, None, None).items;
+        assert_eq!(feed[0].name, "");
+        let comments = contract
+            .get_post_comment_details(1, None, None, None, None)
+            .items;
+        assert_eq!(comments[0].user_address, ghost);
+    }
+
+    #[test]
+    #[should_panic(expected = "Post does not exist!")]
+    fn test_like_missing_post() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.like_post(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Post does not exist!")]
+    fn test_like_comment_on_deleted_post() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        contract.comment_on_post(1, "hi".into(), None, None);
+        contract.delete_post(1);
+        contract.like_comment(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Post does not exist!")]
+    fn test_edit_comment_on_deleted_post() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        contract.comment_on_post(1, "hi".into(), None, None);
+        contract.delete_post(1);
+        contract.edit_comment(1, "bye".into());
+    }
+
+    #[test]
+    fn test_purge_orphan_interactions() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        contract.comment_on_post(1, "kept".into(), None, None);
+        contract.insert_post_like(
+            1,
+            &post::PostLikes {
+                post_id: 7,
+                user_address: robert.clone(),
+                created_at: 0,
+            },
+        );
+        contract.insert_comment(&post::PostComment {
+            comment_id: 7,
+            post_id: 7,
+            user_address: robert.clone(),
+            comment: "orphan".into(),
+            created_at: 0,
+        });
+        contract.post_counter = 7;
+
+        assert_eq!(contract.purge_orphan_interactions(vec![1, 7, 8]), 1);
+        assert_eq!(contract.clear_deleted_posts(100), 0);
+        assert!(!contract.is_post_liked(7, &robert));
+        assert!(contract.comments.get(&7).is_none());
+        assert_eq!(contract.post_comment_count(7), 0);
+        assert_eq!(contract.post_comment_count(1), 1);
+    }
+
+    #[test]
+    fn test_purge_keeps_live_posts() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        assert_eq!(contract.purge_orphan_interactions(vec![1]), 0);
+        contract.create_post("hello".into(), None, None, None);
+        contract.like_post(1);
+        contract.repost(1);
+        assert_eq!(contract.purge_orphan_interactions(vec![2]), 0);
+
+        // A queued ID that is in use again is dropped from the queue untouched
+        contract.deleted_post_ids.push(&1);
+        assert_eq!(contract.clear_deleted_posts(100), 0);
+        assert!(contract.is_post_liked(1, &robert));
+        assert_eq!(contract.get_single_post(1, None, None).repost_count, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Posts migration in progress!")]
+    fn test_purge_during_posts_migration() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        contract.legacy_all_posts.push(&post::PostDetail {
+            post_id: 1,
+            user_address: robert,
+            content: "legacy".into(),
+            created_at: 0,
+        });
+        contract.post_counter = 1;
+        contract.purge_orphan_interactions(vec![1]);
+    }
+
+    #[test]
+    fn test_delete_post() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        let mut ctx = get_context(vec![]);
+        ctx.storage_usage = env::storage_usage();
+        ctx.attached_deposit = 10u128.pow(24);
+        testing_env!(ctx);
+        contract.create_post("hello".into(), None, None, None);
+        assert!(contract.post_storage_deposits.get(&1).unwrap() > 0);
+
+        contract.like_post(1);
+        contract.comment_on_post(1, "hi".into(), None, None);
+        contract.delete_post(1);
+        assert_eq!(contract.clear_deleted_posts(100), 0);
+
+        assert!(contract.get_post(1).is_none());
+        assert!(!contract.is_post_liked(1, &robert));
+        assert!(contract.comments.get(&1).is_none());
+        assert!(contract.post_storage_deposits.get(&1).is_none());
+        assert!(contract
+            .get_user_posts(robert, None, None, None)
+            .items
+            .is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the author or a moderator can delete this post!")]
+    fn test_delete_post_by_other_account() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        register(&mut contract, "jane.testnet");
+        contract.delete_post(1);
+    }
+
+    #[test]
+    fn test_moderator_deletes_post() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        let jane = register(&mut contract, "jane.testnet");
+        contract.add_moderator(jane);
+        contract.delete_post(1);
+        assert!(contract.get_post(1).is_none());
+    }
+
+    #[test]
+    fn test_edit_post() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("first".into(), None, None, None);
+        contract.edit_post(1, "second".into());
+        contract.edit_post(1, "third".into());
+
+        let post = contract.get_single_post(1, None, None);
+        assert_eq!(post.post.content, "third");
+        assert_eq!(post.revision_count, 2);
+        assert!(post.edited_at.is_some());
+
+        let revisions = contract.get_post_revisions(1);
+        assert_eq!(revisions[0].content, "first");
+        assert_eq!(revisions[1].content, "second");
+        assert_eq!(revisions[1].created_at, revisions[0].replaced_at);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the author can edit this post!")]
+    fn test_edit_post_by_other_account() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        register(&mut contract, "jane.testnet");
+        contract.edit_post(1, "hijacked".into());
+    }
+
+    #[test]
+    fn test_edit_and_delete_comment() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        register(&mut contract, "jane.testnet");
+        contract.comment_on_post(1, "frist".into(), None, None);
+        contract.comment_on_post(1, "spam".into(), None, None);
+        contract.edit_comment(1, "first".into());
+
+        let comments = contract
+            .get_post_comment_details(1, None, None, None, None)
+            .items;
+        assert_eq!(comments[0].comment, "first");
+        assert!(comments[0].edited_at.is_some());
+        assert!(comments[1].edited_at.is_none());
+
+        set_signer("robert.testnet");
+        contract.delete_comment(2);
+        let post = contract.get_single_post(1, None, None);
+        assert_eq!(post.comment_count, 1);
+        assert!(contract.comments.get(&2).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the commenter can edit this comment!")]
+    fn test_edit_comment_by_post_author() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        register(&mut contract, "jane.testnet");
+        contract.comment_on_post(1, "hi".into(), None, None);
+        set_signer("robert.testnet");
+        contract.edit_comment(1, "bye".into());
+    }
+
+    #[test]
+    fn test_comment_thread() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        contract.comment_on_post(1, "root".into(), None, None);
+        contract.comment_on_post(1, "reply".into(), Some(1), None);
+        contract.comment_on_post(1, "nested reply".into(), Some(2), None);
+        contract.comment_on_post(1, "second reply".into(), Some(1), None);
+
+        let thread = contract.get_comment_thread(1, Some(1), None);
+        assert_eq!(thread.comment.reply_count, 2);
+        assert_eq!(thread.replies.len(), 2);
+        assert_eq!(thread.replies[0].comment.parent_comment_id, Some(1));
+        assert_eq!(thread.replies[0].comment.reply_count, 1);
+        assert!(thread.replies[0].replies.is_empty());
+        assert_eq!(
+            contract.get_comment_thread(1, None, None).replies[0]
+                .replies
+                .len(),
+            1
+        );
+
+        contract.delete_comment(2);
+        assert!(contract.comments.get(&2).is_none());
+        assert_eq!(contract.clear_deleted_comments(100), 0);
+        assert_eq!(contract.get_single_post(1, None, None).comment_count, 2);
+        assert!(contract.comments.get(&3).is_none());
+        assert_eq!(
+            contract
+                .get_comment_thread(1, None, None)
+                .comment
+                .reply_count,
+            1
+        );
+    }
+
+    #[test]
+    fn test_comment_thread_reply_limit() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        contract.comment_on_post(1, "root".into(), None, None);
+        for i in 0..25 {
+            contract.comment_on_post(1, format!("reply {}", i), Some(1), None);
+        }
+        // Replies to each of the first page of replies
+        for reply_id in 2..=21 {
+            // A fresh context for each batch keeps the mocked gas limit out of the way
+            set_signer("robert.testnet");
+            for i in 0..5 {
+                contract.comment_on_post(1, format!("nested {}", i), Some(reply_id), None);
+            }
+        }
+
+        set_signer("robert.testnet");
+        let thread = contract.get_comment_thread(1, Some(2), None);
+        assert_eq!(thread.replies.len(), 20);
+        assert_eq!(thread.replies_cursor, Some(21));
+        let nested: u64 = thread
+            .replies
+            .iter()
+            .map(|reply| reply.replies.len() as u64)
+            .sum();
+        assert_eq!(nested + 20, MAX_THREAD_REPLIES);
+        assert!(thread.replies[19].replies.is_empty());
+        assert!(thread.replies[19].replies_cursor.is_none());
+
+        let page = contract.get_comment_replies(1, thread.replies_cursor, None, None);
+        assert_eq!(page.items.len(), 5);
+        assert_eq!(page.items[0].comment_id, 22);
+        assert!(page.next_cursor.is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Depth must be at most 5!")]
+    fn test_comment_thread_too_deep() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        contract.comment_on_post(1, "root".into(), None, None);
+        contract.get_comment_thread(1, Some(6), None);
+    }
+
+    #[test]
+    fn test_clear_deleted_comments_in_batches() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        contract.comment_on_post(1, "root".into(), None, None);
+        contract.comment_on_post(1, "reply".into(), Some(1), None);
+        contract.comment_on_post(1, "nested reply".into(), Some(2), None);
+        contract.comment_on_post(1, "other".into(), None, None);
+        contract.like_comment(1);
+        contract.like_comment(3);
+        register(&mut contract, "jane.testnet");
+        contract.like_comment(1);
+
+        set_signer("robert.testnet");
+        contract.delete_comment(1);
+        assert!(contract.comments.get(&1).is_none());
+        let page = contract.get_post_comment_details(
+            1,
+            None,
+            None,
+            Some(post::CommentSortOrder::TopLiked),
+            None,
+        );
+        assert!(page.items.iter().all(|comment| comment.comment_id != 1));
+
+        let mut batches = 0;
+        while contract.clear_deleted_comments(2) > 0 {
+            batches += 1;
+        }
+        assert!(batches > 1);
+        for comment_id in 1..=3 {
+            assert!(contract.comments.get(&comment_id).is_none());
+            assert!(contract.comment_replies.get(&comment_id).is_none());
+            assert!(contract.comment_likers.get(&comment_id).is_none());
+        }
+        assert_eq!(contract.get_single_post(1, None, None).comment_count, 1);
+        assert_eq!(contract.post_top_comments_of(1).len(), 1);
+    }
+
+    #[test]
+    fn test_like_comment() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        for i in 1..=3 {
+            contract.comment_on_post(1, format!("comment {}", i), None, None);
+        }
+        contract.like_comment(2);
+        register(&mut contract, "jane.testnet");
+        contract.like_comment(2);
+        contract.like_comment(3);
+
+        let page = contract.get_post_comment_details(
+            1,
+            None,
+            Some(2),
+            Some(post::CommentSortOrder::TopLiked),
+            Some(robert.clone()),
+        );
+        assert_eq!(page.items[0].comment_id, 2);
+        assert_eq!(page.items[0].like_count, 2);
+        assert_eq!(page.items[0].is_liked, Some(true));
+        assert_eq!(page.items[1].comment_id, 3);
+        assert_eq!(page.items[1].is_liked, Some(false));
+        assert_eq!(page.next_cursor, Some(2));
+
+        contract.like_comment(2);
+        let page = contract.get_post_comment_details(
+            1,
+            Some(1),
+            None,
+            Some(post::CommentSortOrder::TopLiked),
+            None,
+        );
+        assert_eq!(page.items[0].comment_id, 2);
+        assert_eq!(page.items[0].like_count, 1);
+        assert_eq!(page.items[0].is_liked, None);
+    }
+
+    #[test]
+    fn test_react_to_post() {
+        let mut contract = Contract::new();
+        contract.add_reaction_kind("fire".into());
+        let robert = register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        contract.like_post(1);
+        register(&mut contract, "jane.testnet");
+        contract.react_to_post(1, Some("fire".into()));
+
+        set_signer("robert.testnet");
+        contract.react_to_post(1, Some("fire".into()));
+        let post = contract.get_single_post(1, Some(robert.clone()), None);
+        assert_eq!(post.like_count, 0);
+        assert_eq!(post.is_liked, Some(false));
+        assert_eq!(post.reaction_counts.get("fire"), Some(&2));
+        assert_eq!(post.reaction, Some("fire".to_string()));
+
+        // Liking replaces the previous reaction
+        contract.like_post(1);
+        let post = contract.get_single_post(1, Some(robert), None);
+        assert_eq!(post.reaction_counts.get("like"), Some(&1));
+        assert_eq!(post.reaction_counts.get("fire"), Some(&1));
+        assert_eq!(post.reaction, Some("like".to_string()));
+
+        contract.delete_post(1);
+
+        assert_eq!(contract.clear_deleted_posts(100), 0);
+        assert!(contract.post_reactions.get(&1).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Reaction kind does not exist!")]
+    fn test_react_with_unknown_kind() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        contract.react_to_post(1, Some("fire".into()));
+    }
+
+    #[test]
+    fn test_repost() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        let jane = register(&mut contract, "jane.testnet");
+        contract.create_post("jane's".into(), None, None, None);
+        assert!(contract.repost(1));
+        assert!(!contract.repost(1));
+
+        let posts = contract
+            .get_user_posts(jane.clone(), None, None, None)
+            .items;
+        assert_eq!(posts.len(), 2);
+        assert_eq!(posts[0].post.post_id, 1);
+        assert_eq!(posts[0].name, "robert.testnet");
+        assert_eq!(posts[0].repost.as_ref().unwrap().user_address, jane);
+        assert_eq!(posts[0].repost_count, 1);
+        assert_eq!(posts[0].is_reposted, Some(true));
+        assert!(posts[1].repost.is_none());
+        let post = contract.get_single_post(1, Some(robert.clone()), None);
+        assert_eq!(post.is_reposted, Some(false));
+
+        assert!(contract.undo_repost(1));
+        assert!(!contract.undo_repost(1));
+        assert_eq!(contract.get_single_post(1, None, None).repost_count, 0);
+        assert_eq!(
+            contract
+                .get_user_posts(jane.clone(), None, None, None)
+                .items
+                .len(),
+            1
+        );
+
+        // Deleting the original removes its reposts from every timeline
+        contract.repost(1);
+        set_signer("robert.testnet");
+        contract.delete_post(1);
+        assert_eq!(contract.clear_deleted_posts(100), 0);
+        assert_eq!(
+            contract.get_user_posts(jane, None, None, None).items.len(),
+            1
+        );
+        assert!(contract.reposts.get(&4).is_none());
+    }
+
+    #[test]
+    fn test_quote_post() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        register(&mut contract, "jane.testnet");
+        contract.create_post("quoting".into(), Some(1), None, None);
+
+        let post = contract.get_single_post(2, None, None);
+        let quoted_post = post.quoted_post.unwrap();
+        assert!(quoted_post.available);
+        assert_eq!(quoted_post.name, Some(robert.to_string()));
+        assert_eq!(quoted_post.post.unwrap().content, "hello");
+        assert_eq!(contract.get_single_post(1, None, None).quote_count, 1);
+        assert_eq!(
+            contract.get_post_quotes(1, None, None, None).items[0]
+                .post
+                .post_id,
+            2
+        );
+
+        set_signer("robert.testnet");
+        contract.delete_post(1);
+        assert_eq!(contract.clear_deleted_posts(100), 0);
+        let quoted_post = contract.get_single_post(2, None, None).quoted_post.unwrap();
+        assert!(!quoted_post.available);
+        assert!(quoted_post.post.is_none());
+    }
+
+    #[test]
+    fn test_extract_hashtags() {
+        assert_eq!(
+            extract_hashtags("#Rust and #near, #rust again! mail#not #"),
+            vec!["rust".to_string(), "near".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_hashtags() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("hello #near".into(), None, None, None);
+        contract.create_post("#near #rust".into(), None, None, None);
+        contract.create_post("#rust".into(), None, None, None);
+
+        let page = contract.get_posts_by_hashtag("#NEAR".into(), None, None, None);
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.items[0].post.post_id, 2);
+        let trending = contract.get_trending_hashtags(None, None);
+        assert_eq!(trending.len(), 2);
+        assert_eq!(trending[0].count, 2);
+
+        contract.edit_post(1, "hello #sosmed".into());
+        contract.delete_post(3);
+        assert_eq!(
+            contract
+                .get_posts_by_hashtag("near".into(), None, None, None)
+                .items
+                .len(),
+            1
+        );
+        assert!(contract.hashtag_posts.get(&"rust".to_string()).is_some());
+        let trending = contract.get_trending_hashtags(Some(1), None);
+        assert_eq!(trending.len(), 3);
+        assert!(trending.iter().all(|hashtag| hashtag.count == 1));
+    }
+
+    #[test]
+    fn test_mentions() {
+        let mut contract = Contract::new();
+        let jane = register(&mut contract, "jane.testnet");
+        register(&mut contract, "robert.testnet");
+        contract.create_post(
+            "hi @jane.testnet and @nobody.testnet.".into(),
+            None,
+            None,
+            None,
+        );
+        contract.comment_on_post(1, "@jane.testnet @jane.testnet".into(), None, None);
+
+        let post = contract.get_single_post(1, None, None);
+        assert_eq!(post.mentions, vec![jane.clone()]);
+        let page = contract.get_mentions(jane.clone(), None, None);
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.items[0].comment_id, Some(1));
+        assert_eq!(page.items[1].comment_id, None);
+        assert_eq!(page.items[1].post_id, 1);
+
+        contract.delete_comment(1);
+        assert_eq!(
+            contract.get_mentions(jane.clone(), None, None).items.len(),
+            1
+        );
+        contract.delete_post(1);
+        assert_eq!(contract.clear_deleted_posts(100), 0);
+        assert!(contract.get_mentions(jane, None, None).items.is_empty());
+    }
+
+    #[test]
+    fn test_single_post_without_comments() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        contract.comment_on_post(1, "hi".into(), None, None);
+
+        let post = contract.get_single_post(1, None, Some(0));
+        assert_eq!(post.comment_count, 1);
+        assert!(post.comment_details.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_edit_updates_mentions() {
+        let mut contract = Contract::new();
+        let jane = register(&mut contract, "jane.testnet");
+        let alice = register(&mut contract, "alice.testnet");
+        let robert = register(&mut contract, "robert.testnet");
+        contract.create_post("hi @jane.testnet".into(), None, None, None);
+        contract.comment_on_post(1, "cc @alice.testnet".into(), None, None);
+        let jane_mention = contract.get_mentions(jane.clone(), None, None).items[0].mention_id;
+
+        contract.edit_post(1, "hi @jane.testnet and @robert.testnet".into());
+        contract.edit_comment(1, "never mind".into());
+
+        let post = contract.get_single_post(1, None, None);
+        assert_eq!(post.mentions, vec![jane.clone(), robert.clone()]);
+        let comments = contract
+            .get_post_comment_details(1, None, None, None, None)
+            .items;
+        assert!(comments[0].mentions.is_empty());
+        // A mention kept by the edit stays in place in the feed
+        let page = contract.get_mentions(jane, None, None);
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].mention_id, jane_mention);
+        assert_eq!(contract.get_mentions(robert, None, None).items.len(), 1);
+        assert!(contract.get_mentions(alice, None, None).items.is_empty());
+        assert!(contract.comment_mentions.get(&1).is_none());
+    }
+
+    fn image(uri: &str) -> post::Attachment {
+        post::Attachment {
+            uri: uri.into(),
+            mime_type: "image/png".into(),
+            width: Some(640),
+            height: Some(480),
+            alt_text: Some("a picture".into()),
+            content_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_attachments() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("look".into(), None, Some(vec![image("ipfs://cid")]), None);
+        contract.comment_on_post(1, "nice".into(), None, Some(vec![image("ipfs://other")]));
+
+        let post = contract.get_single_post(1, None, None);
+        assert_eq!(post.attachments.len(), 1);
+        assert_eq!(post.attachments[0].uri, "ipfs://cid");
+        let comments = contract.get_post_comment_details(1, None, None, None, None);
+        assert_eq!(comments.items[0].attachments[0].width, Some(640));
+
+        contract.delete_post(1);
+
+        assert_eq!(contract.clear_deleted_posts(100), 0);
+        assert!(contract.post_attachments.get(&1).is_none());
+        assert!(contract.comment_attachments.get(&1).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "At most 4 attachments are allowed!")]
+    fn test_too_many_attachments() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post(
+            "look".into(),
+            None,
+            Some(vec![image("ipfs://cid"); 5]),
+            None,
+        );
+    }
+
+    #[test]
+    fn test_poll() {
+        let mut contract = Contract::new();
+        let robert = register(&mut contract, "robert.testnet");
+        let poll = post::NewPoll {
+            options: vec!["yes".into(), "no".into()],
+            closes_at: 100,
+        };
+        contract.create_post("vote".into(), None, None, Some(poll));
+        contract.vote_poll(1, 1);
+        register(&mut contract, "jane.testnet");
+        contract.vote_poll(1, 1);
+
+        let poll = contract
+            .get_single_post(1, Some(robert), None)
+            .poll
+            .unwrap();
+        assert_eq!(poll.vote_counts, vec![0, 2]);
+        assert_eq!(poll.viewer_vote, Some(1));
+        assert_eq!(poll.has_voted, Some(true));
+        assert!(!poll.closed);
+
+        let mut ctx = get_context(vec![]);
+        ctx.block_timestamp = 100;
+        ctx.storage_usage = env::storage_usage();
+        testing_env!(ctx);
+        let poll = contract.get_single_post(1, None, None).poll.unwrap();
+        assert!(poll.closed);
+        assert_eq!(poll.has_voted, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Already voted in this poll!")]
+    fn test_vote_poll_twice() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        let poll = post::NewPoll {
+            options: vec!["yes".into(), "no".into()],
+            closes_at: 100,
+        };
+        contract.create_post("vote".into(), None, None, Some(poll));
+        contract.vote_poll(1, 0);
+        contract.vote_poll(1, 1);
+    }
+
+    #[test]
+    fn test_delete_post_with_many_reposts() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+        let reposters: Vec<AccountId> = (0..5)
+            .map(|i| {
+                let reposter = register(&mut contract, &format!("reposter{}.testnet", i));
+                contract.repost(1);
+                reposter
+            })
+            .collect();
+
+        set_signer("robert.testnet");
+        contract.delete_post(1);
+        assert_eq!(contract.clear_deleted_posts(100), 0);
+        for reposter in reposters {
+            assert!(!contract.repost_ids.contains_key(&(1, reposter.clone())));
+            assert!(contract.user_posts_of(&reposter).is_empty());
+        }
+        assert!(contract.post_reposts.get(&1).is_none());
+    }
+
+    #[test]
+    fn test_reactions_record_only_for_other_kinds() {
+        let mut contract = Contract::new();
+        contract.add_reaction_kind("fire".into());
+        register(&mut contract, "robert.testnet");
+        contract.create_post("hello".into(), None, None, None);
+
+        contract.like_post(1);
+        assert!(contract.post_reactions.get(&1).is_none());
+        contract.react_to_post(1, Some("fire".into()));
+        assert!(contract.post_reactions.get(&1).is_some());
+        contract.react_to_post(1, None);
+        assert!(contract.post_reactions.get(&1).is_none());
+        assert!(contract.reaction_counts.get(&1).is_none());
+    }
+
+    #[test]
+    fn test_hashtag_usage_across_hours() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("#rust".into(), None, None, None);
+        contract.create_post("no tags".into(), None, None, None);
+
+        let mut ctx = get_context(vec![]);
+        ctx.block_timestamp = 5 * NANOSECONDS_PER_HOUR;
+        ctx.storage_usage = env::storage_usage();
+        testing_env!(ctx);
+        contract.edit_post(2, "now #rust".into());
+        assert_eq!(contract.get_trending_hashtags(Some(6), None)[0].count, 2);
+
+        contract.delete_post(2);
+        let trending = contract.get_trending_hashtags(Some(6), None);
+        assert_eq!(trending.len(), 1);
+        assert_eq!(trending[0].count, 1);
+        assert!(contract.get_trending_hashtags(Some(1), None).is_empty());
+        assert!(contract.post_hashtag_hours.get(&2).is_none());
+    }
+
+    #[test]
+    fn test_clear_deleted_posts_in_batches() {
+        let mut contract = Contract::new();
+        contract.add_reaction_kind("fire".into());
+        let robert = register(&mut contract, "robert.testnet");
+        let poll = post::NewPoll {
+            options: vec!["yes".into(), "no".into()],
+            closes_at: 100,
+        };
+        contract.create_post("hello".into(), None, None, Some(poll));
+        contract.edit_post(1, "hello again".into());
+        for i in 0..6 {
+            register(&mut contract, &format!("user{}.testnet", i));
+            if i % 2 == 0 {
+                contract.like_post(1);
+            } else {
+                contract.react_to_post(1, Some("fire".into()));
+            }
+            contract.vote_poll(1, 0);
+            contract.repost(1);
+            contract.comment_on_post(1, "hi".into(), None, None);
+            contract.comment_on_post(1, "reply".into(), Some(1), None);
+            contract.like_comment(1);
+        }
+        contract.create_post("quoting".into(), Some(1), None, None);
+
+        set_signer("robert.testnet");
+        contract.delete_post(1);
+        assert!(contract.get_post(1).is_none());
+        assert!(contract
+            .get_user_posts(robert, None, None, None)
+            .items
+            .is_empty());
+
+        let mut batches = 0;
+        while contract.clear_deleted_posts(5) > 0 {
+            batches += 1;
+        }
+        assert!(batches > 1);
+        assert_eq!(contract.post_like_count(1), 0);
+        assert!(contract.post_likers.get(&1).is_none());
+        assert!(contract.post_reactions.get(&1).is_none());
+        assert!(contract.reaction_counts.get(&1).is_none());
+        assert!(contract.post_reposts.get(&1).is_none());
+        assert!(contract.post_comment_ids.get(&1).is_none());
+        assert!(contract.post_top_comments.get(&1).is_none());
+        assert!(contract.comments.get(&1).is_none());
+        assert!(contract.comment_likers.get(&1).is_none());
+        assert!(contract.poll_votes.get(&1).is_none());
+        assert!(contract.post_quotes.get(&1).is_none());
+        assert!(contract.post_revisions.get(&1).is_none());
+        assert!(
+            !contract
+                .get_single_post(8, None, None)
+                .quoted_post
+                .unwrap()
+                .available
+        );
+    }
+
+    #[test]
+    fn test_storage_refund_goes_to_payer() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        let mut ctx = get_context(vec![]);
+        ctx.storage_usage = env::storage_usage();
+        ctx.attached_deposit = 10u128.pow(24);
+        testing_env!(ctx);
+        contract.create_post("hello".into(), None, None, None);
+        assert_eq!(
+            contract.post_storage_payers.get(&1),
+            Some("jane.testnet".parse().unwrap())
+        );
+
+        contract.delete_post(1);
+        assert!(contract.post_storage_payers.get(&1).is_none());
+    }
+
+    #[test]
+    fn test_prune_hashtag_usage_in_batches() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        for post in 0..3 {
+            let tags: Vec<String> = (0..10).map(|i| format!("#tag{}_{}", post, i)).collect();
+            contract.create_post(tags.join(" "), None, None, None);
+        }
+
+        let mut ctx = get_context(vec![]);
+        ctx.block_timestamp = (MAX_TRENDING_WINDOW_HOURS + 1) * NANOSECONDS_PER_HOUR;
+        ctx.storage_usage = env::storage_usage();
+        testing_env!(ctx);
+        contract.create_post("#fresh".into(), None, None, None);
+        assert_eq!(contract.hashtag_usage.get(&0).unwrap().len(), 10);
+        contract.create_post("#fresh".into(), None, None, None);
+        assert!(contract.hashtag_usage.get(&0).is_none());
+        assert_eq!(contract.get_trending_hashtags(None, None)[0].count, 2);
+    }
+
+    #[test]
+    fn test_hashtag_hours_expire() {
+        let mut contract = Contract::new();
+        register(&mut contract, "robert.testnet");
+        contract.create_post("#rust".into(), None, None, None);
+        assert!(contract.post_hashtag_hours.get(&1).is_some());
+
+        let mut ctx = get_context(vec![]);
+        ctx.block_timestamp = (MAX_TRENDING_WINDOW_HOURS + 1) * NANOSECONDS_PER_HOUR;
+        ctx.storage_usage = env::storage_usage();
+        testing_env!(ctx);
+        contract.create_post("no tags".into(), None, None, None);
+        assert!(contract.post_hashtag_hours.get(&1).is_none());
+        assert!(contract.hashtag_hour_posts.is_empty());
+
+        contract.edit_post(1, "#near".into());
+        contract.delete_post(1);
+        assert!(contract.post_hashtag_hours.get(&1).is_none());
+        assert!(contract.hashtag_hour_posts.is_empty());
+    }
 }
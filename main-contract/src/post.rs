@@ -1,20 +1,7 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::AccountId;
-
-#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug)]
-#[serde(crate = "near_sdk::serde")]
-pub struct UserAccountDetail {
-    pub address: String,
-    pub name: String,
-    pub profile_image_url: String,
-    pub location: String,
-    pub url: String,
-    pub description: String,
-    pub created_at: u64,
-    pub followers_count: u32,
-    pub following_count: u32,
-}
+use std::collections::BTreeMap;
 
 #[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug)]
 #[serde(crate = "near_sdk::serde")]
@@ -26,7 +13,19 @@ pub struct PostOutputFormat {
     pub comment_count: u64,
     pub like_details: Option<Vec<PostLikes>>,
     pub comment_details: Option<Vec<PostComment>>,
-    pub is_liked: Option<bool>
+    pub is_liked: Option<bool>,
+    pub edited_at: Option<u64>,
+    pub revision_count: u64,
+    pub reaction_counts: BTreeMap<String, u64>,
+    pub reaction: Option<String>,
+    pub repost_count: u64,
+    pub is_reposted: Option<bool>,
+    pub repost: Option<Repost>,
+    pub mentions: Vec<AccountId>,
+    pub attachments: Vec<Attachment>,
+    pub poll: Option<PollOutput>,
+    pub quoted_post: Option<QuotedPost>,
+    pub quote_count: u64,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug)]
@@ -38,6 +37,70 @@ pub struct PostDetail {
     pub created_at: u64,
 }
 
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Attachment {
+    pub uri: String,
+    pub mime_type: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub alt_text: Option<String>,
+    pub content_hash: Option<String>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NewPoll {
+    pub options: Vec<String>,
+    pub closes_at: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Poll {
+    pub options: Vec<String>,
+    pub vote_counts: Vec<u64>,
+    pub closes_at: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PollOutput {
+    pub options: Vec<String>,
+    pub vote_counts: Vec<u64>,
+    pub closes_at: u64,
+    pub closed: bool,
+    pub has_voted: Option<bool>,
+    pub viewer_vote: Option<u64>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct QuotedPost {
+    pub post_id: u64,
+    pub available: bool,
+    pub name: Option<String>,
+    pub profile_image_url: Option<String>,
+    pub post: Option<PostDetail>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Repost {
+    pub repost_id: u64,
+    pub post_id: u64,
+    pub user_address: AccountId,
+    pub created_at: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PostRevision {
+    pub content: String,
+    pub created_at: u64,
+    pub replaced_at: u64,
+}
+
 #[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub struct PostLikes {
@@ -70,5 +133,56 @@ pub struct PostCommentDetailsOutput {
     pub user_address: AccountId,
     pub profile_image_url: String,
     pub comment: String,
-    pub created_at: u64
-}
\ No newline at end of file
+    pub created_at: u64,
+    pub edited_at: Option<u64>,
+    pub parent_comment_id: Option<u64>,
+    pub reply_count: u64,
+    pub like_count: u64,
+    pub is_liked: Option<bool>,
+    pub mentions: Vec<AccountId>,
+    pub attachments: Vec<Attachment>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CommentThread {
+    pub comment: PostCommentDetailsOutput,
+    pub replies: Vec<CommentThread>,
+    pub replies_cursor: Option<u64>,
+}
+
+#[derive(
+    BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone, Copy, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum SortOrder {
+    OldestFirst,
+    NewestFirst,
+}
+
+#[derive(
+    BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone, Copy, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum CommentSortOrder {
+    OldestFirst,
+    NewestFirst,
+    TopLiked,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct HashtagCount {
+    pub tag: String,
+    pub count: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Mention {
+    pub mention_id: u64,
+    pub post_id: u64,
+    pub comment_id: Option<u64>,
+    pub user_address: AccountId,
+    pub created_at: u64,
+}
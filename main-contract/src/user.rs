@@ -28,5 +28,5 @@ pub struct UserFollowers {
 pub struct UserFollowList {
     pub profile_image_url: String,
     pub user_account_id: AccountId,
-    pub is_followed: bool
-}
\ No newline at end of file
+    pub is_followed: bool,
+}